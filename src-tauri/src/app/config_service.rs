@@ -0,0 +1,327 @@
+use crate::app::constants::{config as config_constants, messages, network, paths};
+use crate::app::subscription_service::collect_enabled_nodes;
+use crate::entity::config_model::{Inbound, Outbound, SingBoxConfig};
+use crate::entity::settings_model::UserSettings;
+use crate::entity::snapshot_model::{ConfigDiff, ConfigSnapshot};
+use crate::utils::app_util::{get_template_path, get_work_dir};
+use crate::utils::{config_util, settings_util, snapshot_util};
+use serde_json::Value;
+use std::error::Error;
+use std::path::Path;
+use tracing::{error, info, warn};
+
+// 获取用户设置
+#[tauri::command]
+pub fn get_user_settings() -> UserSettings {
+    settings_util::load_settings()
+}
+
+// 保存用户设置并重新生成配置
+#[tauri::command]
+pub async fn update_user_settings(settings: UserSettings) -> Result<UserSettings, String> {
+    if settings.proxy_port == 0 {
+        return Err("代理端口无效".to_string());
+    }
+    if settings.clash_api_port == 0 || settings.clash_api_port == settings.proxy_port {
+        return Err("Clash API 端口无效".to_string());
+    }
+    update_settings_and_regenerate(move |current| *current = settings).await
+}
+
+// 修改设置后重新生成配置文件
+pub(crate) async fn update_settings_and_regenerate<F>(f: F) -> Result<UserSettings, String>
+where
+    F: FnOnce(&mut UserSettings) + Send,
+{
+    let previous = settings_util::load_settings();
+    let settings = settings_util::update_settings(f)?;
+
+    // 配置生成失败时恢复之前的设置，保持设置与配置文件一致
+    if let Err(e) = regenerate_config().await {
+        let _ = settings_util::update_settings(|current| *current = previous);
+        return Err(format!("生成配置文件失败: {}", e));
+    }
+    Ok(settings)
+}
+
+// 根据模板、订阅节点和用户设置重新生成完整的配置文件
+pub(crate) async fn regenerate_config() -> Result<usize, Box<dyn Error>> {
+    let (enabled_count, nodes) = collect_enabled_nodes()?;
+    let node_count = nodes.len();
+    // 没有可用节点时仍按模板和设置生成配置，代理流量走直连
+    if nodes.is_empty() {
+        warn!("没有启用的订阅节点，生成仅包含直连出站的配置");
+    }
+
+    // 确保目录存在
+    let work_dir = get_work_dir();
+    let dir = Path::new(&work_dir).join("sing-box");
+    if let Err(e) = std::fs::create_dir_all(&dir) {
+        error!("{}: {}", messages::ERR_CREATE_DIR_FAILED, e);
+    }
+
+    // 读取模板文件
+    let template_content = std::fs::read_to_string(get_template_path())
+        .map_err(|e| format!("{}: {}", messages::ERR_READ_FILE_FAILED, e))?;
+    let template = config_util::parse_config(&template_content)?;
+
+    let config = generate_config(template, nodes, &settings_util::load_settings());
+    write_and_verify_config(&config).await?;
+
+    info!("已合并 {} 个订阅的 {} 个节点，配置已保存", enabled_count, node_count);
+    Ok(node_count)
+}
+
+// 写入配置并使用内核检查，检查失败时恢复写入前的配置
+async fn write_and_verify_config(config: &SingBoxConfig) -> Result<(), String> {
+    let config_path = paths::get_config_path();
+    let snapshot_id = config_util::save_config(&config_path, config)?;
+    verify_or_rollback(&config_path, snapshot_id).await
+}
+
+// 使用内核检查刚写入的配置，检查失败时恢复为写入前的快照
+// sing-box check 会启动外部进程，在阻塞线程中执行
+async fn verify_or_rollback(config_path: &Path, snapshot_id: Option<String>) -> Result<(), String> {
+    // 内核尚未下载时无法检查
+    if !paths::get_kernel_path().exists() {
+        return Ok(());
+    }
+
+    let path = config_path.to_path_buf();
+    let result = tokio::task::spawn_blocking(move || config_util::verify_config(&path))
+        .await
+        .map_err(|e| format!("无法验证配置: {}", e))?;
+
+    if let Err(e) = result {
+        if let Some(id) = snapshot_id {
+            let content = snapshot_util::read_snapshot(&id)?;
+            std::fs::write(config_path, content)
+                .map_err(|e| format!("{}: {}", messages::ERR_WRITE_FILE_FAILED, e))?;
+            warn!("新配置未通过检查，已恢复快照 {}", id);
+            return Err(format!("新配置未通过检查，已恢复之前的配置: {}", e));
+        }
+        return Err(e);
+    }
+    Ok(())
+}
+
+// 更换被占用的端口：同时修改设置和当前配置文件
+// 系统代理由内核根据混合入站端口设置，Clash API 地址从设置读取，都会随之更新
+pub(crate) async fn reassign_ports(proxy_port: Option<u16>, clash_api_port: Option<u16>) -> Result<(), String> {
+    let config_path = paths::get_config_path();
+    let mut config = config_util::load_config(&config_path)?;
+
+    if let Some(port) = proxy_port {
+        if let Some(mixed) = config.inbound_by_type_mut(config_constants::DEFAULT_INBOUND_TYPE) {
+            mixed.listen_port = Some(port);
+        }
+    }
+    if let Some(port) = clash_api_port {
+        config.clash_api_mut().external_controller =
+            Some(format!("{}:{}", network::DEFAULT_CLASH_API_ADDRESS, port));
+    }
+
+    let previous = settings_util::load_settings();
+    settings_util::update_settings(|settings| {
+        if let Some(port) = proxy_port {
+            settings.proxy_port = port;
+        }
+        if let Some(port) = clash_api_port {
+            settings.clash_api_port = port;
+        }
+    })?;
+
+    if let Err(e) = write_and_verify_config(&config).await {
+        let _ = settings_util::update_settings(|current| *current = previous);
+        return Err(format!("更新端口失败: {}", e));
+    }
+    info!("端口已更新: 代理端口 {:?}，Clash API 端口 {:?}", proxy_port, clash_api_port);
+    Ok(())
+}
+
+// 获取配置快照列表
+#[tauri::command]
+pub fn list_config_snapshots() -> Vec<ConfigSnapshot> {
+    snapshot_util::list_snapshots()
+}
+
+// 比较快照与当前配置的差异
+#[tauri::command]
+pub fn diff_config_snapshot(id: String) -> Result<Vec<ConfigDiff>, String> {
+    let snapshot: Value = serde_json::from_str(&snapshot_util::read_snapshot(&id)?)
+        .map_err(|e| format!("{}: {}", messages::ERR_INVALID_CONFIG, e))?;
+
+    let config_path = paths::get_config_path();
+    let current: Value = match std::fs::read_to_string(&config_path) {
+        Ok(content) => serde_json::from_str(&content)
+            .map_err(|e| format!("{}: {}", messages::ERR_INVALID_CONFIG, e))?,
+        Err(_) => Value::Null,
+    };
+
+    Ok(snapshot_util::diff_json(&snapshot, &current))
+}
+
+// 将配置恢复为指定的快照，恢复前保存当前配置的快照
+#[tauri::command]
+pub async fn restore_config_snapshot(id: String) -> Result<(), String> {
+    let content = snapshot_util::read_snapshot(&id)?;
+    serde_json::from_str::<Value>(&content).map_err(|e| format!("{}: {}", messages::ERR_INVALID_CONFIG, e))?;
+
+    // 与生成配置相同，恢复后使用内核检查，未通过时回到恢复前的配置
+    let config_path = paths::get_config_path();
+    let previous = snapshot_util::create_snapshot(&config_path)?;
+    std::fs::write(&config_path, content).map_err(|e| format!("{}: {}", messages::ERR_WRITE_FILE_FAILED, e))?;
+    verify_or_rollback(&config_path, previous).await?;
+
+    info!("配置已恢复为快照 {}", id);
+    Ok(())
+}
+
+// 生成配置：相同的模板、节点和设置总是得到相同的配置
+pub fn generate_config(mut template: SingBoxConfig, nodes: Vec<Value>, settings: &UserSettings) -> SingBoxConfig {
+    apply_nodes_to_template(&mut template, nodes);
+    apply_inbounds(&mut template, settings);
+    apply_dns(&mut template, settings);
+
+    let clash_api = template.clash_api_mut();
+    clash_api.external_controller = Some(format!(
+        "{}:{}",
+        network::DEFAULT_CLASH_API_ADDRESS,
+        settings.clash_api_port
+    ));
+    clash_api.default_mode = Some(settings.clash_mode.clone());
+
+    template
+}
+
+// 根据设置生成入站：混合入站始终存在，TUN入站按需添加
+fn apply_inbounds(config: &mut SingBoxConfig, settings: &UserSettings) {
+    // 保留模板中混合入站的 sniff、users 等字段
+    let mut mixed = config
+        .inbounds
+        .iter()
+        .find(|i| i.r#type == config_constants::DEFAULT_INBOUND_TYPE)
+        .cloned()
+        .unwrap_or_else(|| Inbound {
+            r#type: config_constants::DEFAULT_INBOUND_TYPE.to_string(),
+            ..Default::default()
+        });
+    mixed
+        .tag
+        .get_or_insert_with(|| config_constants::DEFAULT_INBOUND_TAG.to_string());
+    mixed.listen = Some(if settings.allow_lan {
+        network::DEFAULT_LISTEN_ADDRESS.to_string()
+    } else {
+        network::LOCAL_LISTEN_ADDRESS.to_string()
+    });
+    mixed.listen_port = Some(settings.proxy_port);
+    mixed.set_system_proxy = if settings.tun_enabled { None } else { Some(true) };
+
+    let tun = config.inbounds.iter().find(|i| i.r#type == "tun").cloned();
+
+    config.inbounds = vec![mixed];
+    if settings.tun_enabled {
+        config.inbounds.push(tun.unwrap_or_else(|| Inbound {
+            r#type: "tun".to_string(),
+            tag: Some("tun-in".to_string()),
+            address: Some(vec![
+                "172.18.0.1/30".to_string(),
+                "fdfe:dcba:9876::1/126".to_string(),
+            ]),
+            auto_route: Some(true),
+            strict_route: Some(true),
+            stack: Some("mixed".to_string()),
+            ..Default::default()
+        }));
+    }
+}
+
+// 根据设置修改DNS策略和服务器地址
+fn apply_dns(config: &mut SingBoxConfig, settings: &UserSettings) {
+    let dns = match config.dns.as_mut() {
+        Some(dns) => dns,
+        None => return,
+    };
+
+    // 只修改模板中已经指定了策略的DNS服务器
+    let strategy = if settings.prefer_ipv6 { "prefer_ipv6" } else { "ipv4_only" };
+    for server in dns.servers.iter_mut() {
+        if server.strategy.is_some() {
+            server.strategy = Some(strategy.to_string());
+        }
+
+        let custom = match server.tag.as_deref() {
+            Some("dns_proxy") => settings.dns.proxy.as_ref(),
+            Some("dns_direct") => settings.dns.direct.as_ref(),
+            Some("dns_resolver") => settings.dns.resolver.as_ref(),
+            _ => None,
+        };
+        if let Some(address) = custom.filter(|a| !a.trim().is_empty()) {
+            server.address = Some(address.trim().to_string());
+        }
+    }
+    if dns.strategy.is_some() {
+        dns.strategy = Some(strategy.to_string());
+    }
+}
+
+// 将节点添加到模板配置中，并更新各选择器的节点列表
+fn apply_nodes_to_template(config: &mut SingBoxConfig, nodes: Vec<Value>) {
+    let mut outbounds = Vec::with_capacity(nodes.len());
+    for node in nodes {
+        match serde_json::from_value::<Outbound>(node) {
+            Ok(outbound) => outbounds.push(outbound),
+            Err(e) => warn!("节点格式无效，已跳过: {}", e),
+        }
+    }
+    let node_tags: Vec<String> = outbounds.iter().map(|o| o.tag.clone()).collect();
+
+    // 更新"自动选择"的节点列表，没有节点时使用直连，urltest 的列表不能为空
+    if let Some(auto_select) = config.outbound_mut("自动选择") {
+        if auto_select.outbounds.is_some() {
+            auto_select.outbounds = Some(if node_tags.is_empty() {
+                vec![config_constants::DIRECT_OUTBOUND_TAG.to_string()]
+            } else {
+                node_tags.clone()
+            });
+        }
+    }
+
+    // 更新"手动切换"的节点列表
+    if let Some(proxy_select) = config.outbound_mut("手动切换") {
+        if proxy_select.outbounds.is_some() {
+            let mut tags = vec!["自动选择".to_string()];
+            tags.extend(node_tags.iter().cloned());
+            proxy_select.outbounds = Some(tags);
+        }
+    }
+
+    // 更新其他选择器的outbounds列表
+    update_selector_outbounds(&mut config.outbounds, &node_tags);
+
+    // 将节点添加到outbounds数组末尾
+    config.outbounds.extend(outbounds);
+}
+
+// 更新所有选择器的outbounds列表
+fn update_selector_outbounds(outbounds: &mut [Outbound], node_tags: &[String]) {
+    // 处理所有selector类型的出站
+    for outbound in outbounds.iter_mut() {
+        if outbound.r#type != "selector" {
+            continue;
+        }
+        if outbound.tag == "手动切换" || outbound.tag == "自动选择" {
+            continue; // 已在前面单独处理
+        }
+
+        if let Some(array) = outbound.outbounds.as_mut() {
+            // 保留前两个元素（通常是proxy和自动选择）
+            if array.len() >= 2 {
+                array.truncate(2);
+            }
+
+            // 添加所有节点标签
+            array.extend(node_tags.iter().cloned());
+        }
+    }
+}
@@ -7,12 +7,45 @@
 pub mod process {
     /// Windows 创建进程时隐藏控制台窗口的标志
     pub const CREATE_NO_WINDOW: u32 = 0x08000000;
+
+    /// 内核可执行文件名
+    #[cfg(windows)]
+    pub const KERNEL_EXE_NAME: &str = "sing-box.exe";
+    #[cfg(not(windows))]
+    pub const KERNEL_EXE_NAME: &str = "sing-box";
     
     /// 进程超时和延迟常量（秒）
     pub const GRACEFUL_TIMEOUT: u64 = 5;
     pub const HEALTH_CHECK_INTERVAL: u64 = 30;
     pub const MAX_RESTART_ATTEMPTS: u32 = 3;
     pub const RESTART_DELAY: u64 = 1;
+    pub const READY_TIMEOUT: u64 = 15;
+    /// 自动重启退避延迟的上限
+    pub const RESTART_BACKOFF_MAX: u64 = 60;
+
+    /// 就绪检查的轮询间隔（毫秒）
+    pub const READY_POLL_INTERVAL_MS: u64 = 200;
+
+    /// 启动失败时附带的内核输出行数
+    pub const OUTPUT_TAIL_LINES: usize = 20;
+
+    /// 内存中保留的内核输出行数
+    pub const OUTPUT_BUFFER_LINES: usize = 1000;
+
+    /// 内核日志文件大小上限（字节），超过后轮转
+    pub const KERNEL_LOG_MAX_SIZE: u64 = 5 * 1024 * 1024;
+
+    /// 保留的历史内核日志文件数量
+    pub const KERNEL_LOG_MAX_FILES: usize = 3;
+
+    /// 保留的内核崩溃记录数量
+    pub const MAX_CRASH_RECORDS: usize = 20;
+
+    /// 内核资源占用的采样间隔（秒）
+    pub const METRICS_INTERVAL: u64 = 5;
+
+    /// 保留的资源占用采样数量，按采样间隔约为一小时
+    pub const METRICS_HISTORY: usize = 720;
 }
 
 /// 文件路径常量
@@ -20,10 +53,22 @@ pub mod paths {
     use std::path::PathBuf;
     use crate::utils::app_util::get_work_dir;
 
-    /// 获取 Sing-Box 可执行文件路径
+    /// 获取 Sing-Box 可执行文件路径，已选择版本时使用对应版本目录中的内核
     pub fn get_kernel_path() -> PathBuf {
-        let work_dir = get_work_dir();
-        PathBuf::from(&work_dir).join("sing-box").join("sing-box.exe")
+        match crate::utils::kernel_version_util::active_version() {
+            Some(version) => get_kernel_versions_dir().join(version).join(super::process::KERNEL_EXE_NAME),
+            None => get_kernel_work_dir().join(super::process::KERNEL_EXE_NAME),
+        }
+    }
+
+    /// 获取内核版本安装目录
+    pub fn get_kernel_versions_dir() -> PathBuf {
+        get_kernel_work_dir().join("versions")
+    }
+
+    /// 获取内核版本状态文件路径
+    pub fn get_kernel_versions_state_path() -> PathBuf {
+        get_kernel_versions_dir().join("state.json")
     }
 
     /// 获取 Sing-Box 工作目录
@@ -37,12 +82,51 @@ pub mod paths {
         let work_dir = get_work_dir();
         PathBuf::from(&work_dir).join("sing-box").join("config.json")
     }
+
+    /// 获取订阅注册表路径
+    pub fn get_subscriptions_path() -> PathBuf {
+        let work_dir = get_work_dir();
+        PathBuf::from(&work_dir).join("subscriptions.json")
+    }
+
+    /// 获取订阅节点缓存目录
+    pub fn get_subscription_nodes_dir() -> PathBuf {
+        let work_dir = get_work_dir();
+        PathBuf::from(&work_dir).join("subscriptions")
+    }
+
+    /// 获取配置快照目录
+    pub fn get_config_snapshots_dir() -> PathBuf {
+        let work_dir = get_work_dir();
+        PathBuf::from(&work_dir).join("snapshots")
+    }
+
+    /// 获取内核PID文件路径
+    pub fn get_kernel_pid_path() -> PathBuf {
+        let work_dir = get_work_dir();
+        PathBuf::from(&work_dir).join("kernel.pid")
+    }
+
+    /// 获取内核日志文件路径
+    pub fn get_kernel_log_path() -> PathBuf {
+        let work_dir = get_work_dir();
+        PathBuf::from(&work_dir).join("logs").join("kernel.log")
+    }
+
+    /// 获取用户设置文件路径
+    pub fn get_settings_path() -> PathBuf {
+        let work_dir = get_work_dir();
+        PathBuf::from(&work_dir).join("settings.json")
+    }
 }
 
 /// 网络常量
 pub mod network {
     /// 默认监听地址
     pub const DEFAULT_LISTEN_ADDRESS: &str = "0.0.0.0";
+
+    /// 仅本机访问时的监听地址
+    pub const LOCAL_LISTEN_ADDRESS: &str = "127.0.0.1";
     
     /// 默认代理端口
     pub const DEFAULT_PROXY_PORT: u16 = 12080;
@@ -53,18 +137,52 @@ pub mod network {
     /// 默认 Clash API 端口
     pub const DEFAULT_CLASH_API_PORT: u16 = 12081;
     
+    /// 自动选择端口时向后查找的端口数量
+    pub const PORT_SEARCH_RANGE: u16 = 100;
+
     /// 默认 API Token
     pub const DEFAULT_API_TOKEN: &str = "";
     
     /// 网络请求超时时间（秒）
     pub const HTTP_TIMEOUT_SECONDS: u64 = 30;
+
+    /// 默认下载镜像，按顺序尝试，空字符串表示直接从 GitHub 下载
+    pub const DEFAULT_DOWNLOAD_MIRRORS: &[&str] = &["https://gh-proxy.com/", ""];
+
+    /// 每个镜像的下载重试次数
+    pub const DOWNLOAD_RETRIES: u32 = 3;
+
+    /// 下载重试的初始间隔（秒），之后每次翻倍
+    pub const DOWNLOAD_RETRY_DELAY: u64 = 1;
+
+    /// 下载进度的上报间隔（毫秒）
+    pub const DOWNLOAD_PROGRESS_INTERVAL_MS: u64 = 200;
+
+    /// 内核下载的ID，用于取消下载
+    pub const KERNEL_DOWNLOAD_ID: &str = "kernel";
+
+    /// 应用更新下载的ID
+    pub const UPDATE_DOWNLOAD_ID: &str = "update";
 }
 
 /// API 常量
 pub mod api {
-    /// GitHub API URL
-    pub const GITHUB_API_URL: &str = "https://api.github.com/repos/xinggaoya/sing-box-windows/releases/latest";
+    /// 应用发布列表 API
+    pub const GITHUB_RELEASES_URL: &str = "https://api.github.com/repos/xinggaoya/sing-box-windows/releases";
+
+    /// 启动后首次检查更新的延迟（秒）
+    pub const UPDATE_CHECK_DELAY: u64 = 60;
+
+    /// 定期检查更新的间隔（秒）
+    pub const UPDATE_CHECK_INTERVAL: u64 = 6 * 60 * 60;
     
+    /// sing-box 发布列表 API
+    pub const KERNEL_RELEASES_URL: &str = "https://api.github.com/repos/SagerNet/sing-box/releases";
+
+    /// 应用更新签名公钥，即 tauri signer generate 生成的 base64 公钥
+    /// 对应的私钥保存在发布流程的 TAURI_SIGNING_PRIVATE_KEY 中
+    pub const UPDATE_PUBLIC_KEY: &str = include_str!("../../update-public.key");
+
     /// 用户代理
     pub const USER_AGENT: &str = "sing-box-windows";
 }
@@ -77,6 +195,7 @@ pub mod messages {
     pub const ERR_GET_VERSION_FAILED: &str = "获取版本信息失败";
     pub const ERR_CONFIG_READ_FAILED: &str = "读取配置文件失败";
     pub const ERR_DOWNLOAD_FAILED: &str = "下载失败";
+    pub const ERR_DOWNLOAD_CANCELLED: &str = "下载已取消";
     pub const ERR_SUBSCRIPTION_FAILED: &str = "下载订阅失败";
     pub const ERR_PROCESS_SUBSCRIPTION_FAILED: &str = "处理订阅内容失败";
     pub const ERR_GET_EXE_PATH_FAILED: &str = "获取当前程序路径失败";
@@ -118,6 +237,12 @@ pub mod config {
     
     /// 默认的 Inbound 类型
     pub const DEFAULT_INBOUND_TYPE: &str = "mixed";
+
+    /// 直连出站标签
+    pub const DIRECT_OUTBOUND_TAG: &str = "direct";
+
+    /// 保留的配置快照数量
+    pub const MAX_SNAPSHOTS: usize = 20;
 }
 
 /// 日志常量
@@ -158,6 +283,24 @@ pub mod registry {
     pub const PROXY_SERVER: &str = "ProxyServer";
 }
 
+/// 订阅相关常量
+pub mod subscription {
+    /// 自动更新调度器的检查间隔（秒）
+    pub const SCHEDULER_TICK_SECS: u64 = 60;
+
+    /// 允许设置的最小自动更新间隔（分钟）
+    pub const MIN_UPDATE_INTERVAL: u64 = 5;
+
+    /// 自动更新失败后的重试间隔（分钟）
+    pub const RETRY_INTERVAL: u64 = 10;
+
+    /// 已用流量达到该百分比时提示流量即将用尽
+    pub const QUOTA_WARNING_PERCENT: u64 = 90;
+
+    /// 距离到期少于该天数时提示订阅即将到期
+    pub const EXPIRE_WARNING_DAYS: u64 = 3;
+}
+
 /// 服务器默认配置
 pub mod server {
     /// 默认主机地址
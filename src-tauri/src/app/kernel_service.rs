@@ -1,20 +1,24 @@
 use crate::process::manager::ProcessManager;
+use crate::entity::github_model::{Asset, Release};
+use crate::entity::kernel_model::{InstalledKernel, KernelInstallRecord, KernelRelease};
+use crate::process::{CrashRecord, KernelMetrics};
 use std::sync::Arc;
-use tracing::{error, info};
+use tracing::{error, info, warn};
 use serde_json::json;
+use crate::utils::file_util::extract_archive;
+use crate::utils::{download_util, kernel_asset_util, kernel_version_util, settings_util, verify_util};
 use std::path::Path;
-use crate::utils::app_util::get_work_dir;
-use crate::utils::file_util::{ unzip_file};
-use std::os::windows::process::CommandExt;
 use tauri::Emitter;
-use crate::app::constants::{paths, process, messages, network};
-use tauri::{Runtime, Window};
+use crate::app::constants::{api, messages, network, paths};
+use crate::app::constants::process;
+use tauri::{AppHandle, Runtime, Window};
 use tokio::task;
-use tokio::sync::mpsc;
+use tokio::sync::{broadcast, mpsc};
 use futures_util::StreamExt;
 use tokio_tungstenite::{connect_async, tungstenite::protocol::Message};
 use url::Url;
 use serde_json::Value;
+use serde::Serialize;
 
 // 全局进程管理器
 lazy_static::lazy_static! {
@@ -24,25 +28,46 @@ lazy_static::lazy_static! {
 // 检查内核版本
 #[tauri::command]
 pub async fn check_kernel_version() -> Result<String, String> {
-    let kernel_path = paths::get_kernel_path();
+    kernel_version_util::kernel_version_info(&paths::get_kernel_path())
+}
 
-    if !kernel_path.exists() {
-        return Err(messages::ERR_KERNEL_NOT_FOUND.to_string());
-    }
+// 获取已安装的内核版本
+#[tauri::command]
+pub async fn list_kernel_versions() -> Vec<InstalledKernel> {
+    task::spawn_blocking(|| {
+        kernel_version_util::migrate_legacy_kernel();
+        kernel_version_util::list_installed()
+    })
+    .await
+    .unwrap_or_default()
+}
 
-    let output = std::process::Command::new(kernel_path)
-        .arg("version")
-        .creation_flags(process::CREATE_NO_WINDOW)
-        .output()
-        .map_err(|e| format!("{}: {}", messages::ERR_VERSION_CHECK_FAILED, e))?;
+// 切换内核版本，内核正在运行时使用新版本重启
+#[tauri::command]
+pub async fn switch_kernel_version(version: String) -> Result<(), String> {
+    kernel_version_util::set_active(&version)?;
+    restart_if_running().await
+}
 
-    if !output.status.success() {
-        let error = String::from_utf8_lossy(&output.stderr);
-        return Err(format!("{}: {}", messages::ERR_GET_VERSION_FAILED, error));
-    }
+// 回滚到切换前使用的内核版本，返回回滚后的版本
+#[tauri::command]
+pub async fn rollback_kernel_version() -> Result<String, String> {
+    let version = kernel_version_util::rollback()?;
+    restart_if_running().await?;
+    Ok(version)
+}
+
+// 删除已安装的内核版本
+#[tauri::command]
+pub async fn remove_kernel_version(version: String) -> Result<(), String> {
+    kernel_version_util::remove_version(&version)
+}
 
-    let version_info = String::from_utf8_lossy(&output.stdout);
-    Ok(version_info.to_string())
+async fn restart_if_running() -> Result<(), String> {
+    if PROCESS_MANAGER.is_running().await {
+        PROCESS_MANAGER.restart().await.map_err(|e| e.to_string())?;
+    }
+    Ok(())
 }
 
 // 运行内核
@@ -63,6 +88,60 @@ pub async fn restart_kernel() -> Result<(), String> {
     PROCESS_MANAGER.restart().await.map_err(|e| e.to_string())
 }
 
+// 获取最近的内核输出
+#[tauri::command]
+pub async fn get_kernel_logs(lines: Option<usize>) -> Vec<String> {
+    PROCESS_MANAGER.get_output(lines.unwrap_or(process::OUTPUT_TAIL_LINES))
+}
+
+// 获取内核崩溃记录
+#[tauri::command]
+pub async fn get_kernel_crashes() -> Vec<CrashRecord> {
+    PROCESS_MANAGER.get_crashes()
+}
+
+// 获取内核资源占用的历史采样
+#[tauri::command]
+pub async fn get_kernel_metrics() -> Vec<KernelMetrics> {
+    PROCESS_MANAGER.get_metrics_history()
+}
+
+// 将内核状态变化、崩溃和资源占用事件转发到前端
+pub fn start_kernel_event_bridge<R: Runtime>(app: AppHandle<R>) {
+    forward_events(app.clone(), PROCESS_MANAGER.subscribe_status(), "kernel-status");
+    forward_events(app.clone(), PROCESS_MANAGER.subscribe_crashes(), "kernel-crashed");
+    forward_events(app, PROCESS_MANAGER.subscribe_metrics(), "kernel-metrics");
+
+    // 定时采样资源占用，内核未运行时跳过
+    tauri::async_runtime::spawn(async {
+        let mut interval = tokio::time::interval(std::time::Duration::from_secs(process::METRICS_INTERVAL));
+        loop {
+            interval.tick().await;
+            PROCESS_MANAGER.sample_metrics().await;
+        }
+    });
+}
+
+fn forward_events<R, T>(app: AppHandle<R>, mut events: broadcast::Receiver<T>, event: &'static str)
+where
+    R: Runtime,
+    T: Serialize + Clone + Send + 'static,
+{
+    tauri::async_runtime::spawn(async move {
+        loop {
+            match events.recv().await {
+                Ok(payload) => {
+                    let _ = app.emit(event, payload);
+                }
+                Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                    warn!("丢失 {} 条 {} 事件", skipped, event);
+                }
+                Err(broadcast::error::RecvError::Closed) => break,
+            }
+        }
+    });
+}
+
 // 获取进程状态
 #[tauri::command]
 pub async fn get_process_status() -> serde_json::Value {
@@ -74,163 +153,197 @@ pub async fn get_process_status() -> serde_json::Value {
     })
 }
 
-// 下载内核
+// 获取 GitHub 上发布的内核版本
+#[tauri::command]
+pub async fn list_kernel_releases(include_prerelease: Option<bool>) -> Result<Vec<KernelRelease>, String> {
+    let include_prerelease = include_prerelease.unwrap_or(false);
+    let releases: Vec<Release> = fetch_github(&format!("{}?per_page=30", api::KERNEL_RELEASES_URL)).await?;
+
+    Ok(releases
+        .into_iter()
+        .filter(|release| !release.draft && (include_prerelease || !release.prerelease))
+        .map(|release| {
+            let version = release.tag_name.trim_start_matches('v').to_string();
+            KernelRelease {
+                installed: kernel_version_util::is_installed(&version),
+                variants: kernel_asset_util::platform_variants(&release.assets, &version),
+                tag: release.tag_name,
+                version,
+                name: release.name,
+                prerelease: release.prerelease,
+                published_at: release.published_at,
+            }
+        })
+        .collect())
+}
+
+// 下载最新版本的内核并切换到该版本
 #[tauri::command]
 pub async fn download_latest_kernel(window: tauri::Window) -> Result<(), String> {
-    let work_dir = get_work_dir();
-    info!("当前工作目录: {}", work_dir);
+    emit_progress(&window, "checking", 0, "正在获取最新版本信息...");
+    let release: Release = fetch_github(&format!("{}/latest", api::KERNEL_RELEASES_URL)).await?;
+    let version = install_release(&window, &release).await?;
 
-    let path = Path::new(&work_dir).join("sing-box/");
-    info!("目标下载目录: {}", path.display());
+    kernel_version_util::set_active(&version)?;
+    restart_if_running().await
+}
 
-    // 如果目录已存在，先检查是否为有效目录
-    if path.exists() {
-        if !path.is_dir() {
-            error!("sing-box 路径存在但不是目录");
-            return Err("sing-box 路径存在但不是目录".to_string());
-        }
+// 安装指定标签的内核版本，可以是预发布版本；activate 为 true 时安装后切换到该版本
+#[tauri::command]
+pub async fn install_kernel_version(
+    window: tauri::Window,
+    tag: String,
+    activate: Option<bool>,
+) -> Result<String, String> {
+    emit_progress(&window, "checking", 0, &format!("正在获取版本 {} 的信息...", tag));
+    let url = format!("{}/tags/{}", api::KERNEL_RELEASES_URL, urlencoding::encode(&tag));
+    let release: Release = fetch_github(&url).await?;
+    let version = install_release(&window, &release).await?;
+
+    if activate.unwrap_or(false) {
+        kernel_version_util::set_active(&version)?;
+        restart_if_running().await?;
     }
+    Ok(version)
+}
 
-    // 确保目录存在
-    if let Err(e) = std::fs::create_dir_all(&path) {
-        error!("创建目录失败: {}", e);
-        return Err(format!("创建目录失败: {}", e));
+// 校验下载的资源，返回安装记录；没有发布校验值时记录为未校验
+async fn verify_download(release: &Release, asset: &Asset, path: &Path) -> Result<KernelInstallRecord, String> {
+    let expected = verify_util::expected_sha256(&release.assets, asset).await?;
+    let sha256 = verify_util::verify_sha256(path, expected.as_deref())?;
+    Ok(KernelInstallRecord {
+        asset: asset.name.clone(),
+        sha256,
+        verified: expected.is_some(),
+    })
+}
+
+// 请求 GitHub API 并解析返回的JSON
+async fn fetch_github<T: serde::de::DeserializeOwned>(url: &str) -> Result<T, String> {
+    let response = reqwest::Client::new()
+        .get(url)
+        .header("User-Agent", api::USER_AGENT)
+        .send()
+        .await
+        .map_err(|e| format!("获取版本信息失败: {}", e))?;
+
+    if !response.status().is_success() {
+        return Err(format!("获取版本信息失败: {}", response.status()));
     }
-    info!("已确保下载目录存在");
 
-    info!("正在准备下载最新版本...");
-    // 发送进度事件
+    response.json().await.map_err(|e| format!("解析版本信息失败: {}", e))
+}
+
+fn emit_progress(window: &tauri::Window, status: &str, progress: u32, message: &str) {
     let _ = window.emit(
         "download-progress",
         json!({
-            "status": "checking",
-            "progress": 0,
-            "message": "正在获取最新版本信息..."
+            "status": status,
+            "progress": progress,
+            "message": message
         }),
     );
+}
 
-    // 获取最新版本信息
-    let client = reqwest::Client::new();
-    let releases_url = "https://api.github.com/repos/SagerNet/sing-box/releases/latest";
-    let response = client
-        .get(releases_url)
-        .header("User-Agent", "sing-box-windows")
-        .send()
-        .await
-        .map_err(|e| format!("获取版本信息失败: {}", e))?;
+// 下载发布版本并解压到对应的版本目录，返回版本号
+// 已安装的版本不会重复下载
+async fn install_release(window: &tauri::Window, release: &Release) -> Result<String, String> {
+    let version = release.tag_name.trim_start_matches('v').to_string();
+    let version_dir = kernel_version_util::version_dir(&version)?;
 
-    let release: serde_json::Value = response
-        .json()
+    // 先迁移旧的内核，使其可以作为回滚目标
+    task::spawn_blocking(kernel_version_util::migrate_legacy_kernel)
         .await
-        .map_err(|e| format!("解析版本信息失败: {}", e))?;
-
-    // 获取版本号
-    let version = release["tag_name"]
-        .as_str()
-        .ok_or("无法获取版本号")?
-        .trim_start_matches('v')
-        .to_string();
-
-    // 获取当前系统平台和架构
-    let platform = std::env::consts::OS;
-    let mut arch = std::env::consts::ARCH;
-    if arch == "x86_64" {
-        arch = "amd64";
-    }
-
-    // 构建目标文件名
-    let target_asset_name = format!("sing-box-{}-{}-{}.zip", version, platform, arch);
-    info!("目标文件名: {}", target_asset_name);
-
-    // 查找Windows版本资源
-    let assets = release["assets"].as_array().ok_or("无法获取发布资源")?;
-    let asset = assets
-        .iter()
-        .find(|asset| {
-            if let Some(name) = asset["name"].as_str() {
-                name.contains("windows-amd64") && name.ends_with(".zip")
-            } else {
-                false
-            }
-        })
-        .ok_or("未找到适用于Windows的资源")?;
+        .map_err(|e| e.to_string())?;
 
-    // 获取下载链接
-    let original_url = asset["browser_download_url"]
-        .as_str()
-        .ok_or("无法获取下载链接")?;
+    if kernel_version_util::is_installed(&version) {
+        info!("内核版本 {} 已安装", version);
+        emit_progress(window, "completed", 100, &format!("版本 {} 已安装", version));
+        return Ok(version);
+    }
 
-    info!("找到下载链接: {}", original_url);
+    // 根据系统、架构和变体选择资源
+    let variant = settings_util::load_settings().kernel_variant;
+    let (asset, format) = kernel_asset_util::resolve_asset(&release.assets, &version, variant.as_deref())?;
+    info!("找到下载链接: {}", asset.browser_download_url);
 
-    let download_path = Path::new(&path).join(&target_asset_name);
+    // 下载到版本目录之外，解压时整个版本目录会被替换
+    let download_path = paths::get_kernel_versions_dir().join(&asset.name);
     info!("目标下载路径: {}", download_path.display());
+    emit_progress(window, "downloading", 20, &format!("开始下载文件: {}", asset.name));
 
-    // 发送进度事件
-    let _ = window.emit(
-        "download-progress",
-        json!({
-            "status": "downloading",
-            "progress": 20,
-            "message": format!("开始下载文件: {}", target_asset_name)
-        }),
-    );
-
-    // 下载文件
+    // 下载文件，失败时保留已下载的部分，再次安装时继续下载
     let window_clone = window.clone();
-    if let Err(e) = crate::utils::file_util::download_with_fallback(
-        original_url, 
-        download_path.to_str().unwrap(), 
+    if let Err(e) = download_util::download(
+        network::KERNEL_DOWNLOAD_ID,
+        &asset.browser_download_url,
+        &download_path,
         move |progress| {
-            let real_progress = 20 + (progress as f64 * 0.6) as u32; // 20-80%的进度用于下载
+            let real_progress = 20 + progress.percent * 6 / 10; // 20-80%的进度用于下载
             let _ = window_clone.emit(
                 "download-progress",
                 json!({
                     "status": "downloading",
                     "progress": real_progress,
-                    "message": format!("正在下载: {}%", progress)
+                    "downloaded": progress.downloaded,
+                    "total": progress.total,
+                    "speed": progress.speed,
+                    "message": format!("正在下载: {}%", progress.percent)
                 }),
             );
-        }).await {
+        },
+    )
+    .await
+    {
         error!("下载失败: {}", e);
+        if e == messages::ERR_DOWNLOAD_CANCELLED {
+            emit_progress(window, "cancelled", 0, &e);
+            return Err(e);
+        }
         return Err(format!(
-            "下载失败: {}。\n您可以尝试手动下载：\n1. 访问 https://github.com/SagerNet/sing-box/releases/latest\n2. 下载 {}\n3. 解压并将文件放置在 {}/sing-box/ 目录下",
-            e, target_asset_name, get_work_dir()
+            "下载失败: {}。\n您可以尝试手动下载：\n1. 访问 {}\n2. 下载 {}\n3. 解压并将其中的文件放置在 {} 目录下",
+            e,
+            release.html_url,
+            asset.name,
+            version_dir.display()
         ));
     }
 
-    // 解压文件
-    info!("开始解压文件...");
-    // 发送进度事件
-    let _ = window.emit(
-        "download-progress",
-        json!({
-            "status": "extracting",
-            "progress": 80,
-            "message": "正在解压文件..."
-        }),
-    );
-
-    let out_path = Path::new(&work_dir).join("sing-box");
-    match unzip_file(download_path.to_str().unwrap(), out_path.to_str().unwrap()).await {
-        Ok(_) => {
-            info!("内核已下载并解压到: {}", out_path.display());
-            // 发送完成事件
-            let _ = window.emit(
-                "download-progress",
-                json!({
-                    "status": "completed",
-                    "progress": 100,
-                    "message": "下载完成！"
-                }),
-            );
-        }
+    // 与发布的校验值比对，防止镜像返回被篡改的文件
+    emit_progress(window, "verifying", 80, "正在校验文件...");
+    let verified = match verify_download(release, asset, &download_path).await {
+        Ok(verified) => verified,
         Err(e) => {
-            error!("解压文件失败: {}", e);
-            return Err(format!("解压文件失败: {}", e));
+            error!("{}", e);
+            let _ = std::fs::remove_file(&download_path);
+            return Err(e);
         }
+    };
+
+    // 解压文件，资源中的文件都在 sing-box-<版本>-<平台>/ 目录下，解压时去掉这一层
+    info!("开始解压文件...");
+    emit_progress(window, "extracting", 85, "正在解压文件...");
+    let result = extract_archive(&download_path, &version_dir, format, 1).await;
+    let _ = std::fs::remove_file(&download_path);
+    if let Err(e) = result {
+        error!("解压文件失败: {}", e);
+        return Err(format!("解压文件失败: {}", e));
     }
 
-    Ok(())
+    // 确认解压出的内核可以运行
+    let kernel_path = kernel_version_util::version_kernel_path(&version)?;
+    if let Err(e) = kernel_version_util::kernel_version_info(&kernel_path) {
+        let _ = std::fs::remove_dir_all(&version_dir);
+        return Err(format!("内核无法运行: {}", e));
+    }
+
+    if let Err(e) = kernel_version_util::write_install_record(&version, &verified) {
+        warn!("保存内核安装记录失败: {}", e);
+    }
+
+    info!("内核 {} 已下载并解压到: {}", version, version_dir.display());
+    emit_progress(window, "completed", 100, "下载完成！");
+    Ok(version)
 }
 
 /// 启动WebSocket数据中继
@@ -254,7 +367,7 @@ async fn start_traffic_relay<R: Runtime>(window: Window<R>) -> Result<(), String
     // 启动WebSocket连接和数据处理任务
     let _handle = task::spawn(async move {
         let url = Url::parse(&format!("ws://127.0.0.1:{}/traffic?token={}", 
-                                     network::DEFAULT_CLASH_API_PORT, token)).unwrap();
+                                     settings_util::clash_api_port(), token)).unwrap();
         
         match connect_async(url).await {
             Ok((ws_stream, _)) => {
@@ -302,7 +415,7 @@ async fn start_memory_relay<R: Runtime>(window: Window<R>) -> Result<(), String>
     // 启动WebSocket连接和数据处理任务
     let _handle = task::spawn(async move {
         let url = Url::parse(&format!("ws://127.0.0.1:{}/memory?token={}", 
-                                     network::DEFAULT_CLASH_API_PORT, token)).unwrap();
+                                     settings_util::clash_api_port(), token)).unwrap();
         
         match connect_async(url).await {
             Ok((ws_stream, _)) => {
@@ -350,7 +463,7 @@ async fn start_logs_relay<R: Runtime>(window: Window<R>) -> Result<(), String> {
     // 启动WebSocket连接和数据处理任务
     let _handle = task::spawn(async move {
         let url = Url::parse(&format!("ws://127.0.0.1:{}/logs?token={}", 
-                                     network::DEFAULT_CLASH_API_PORT, token)).unwrap();
+                                     settings_util::clash_api_port(), token)).unwrap();
         
         match connect_async(url).await {
             Ok((ws_stream, _)) => {
@@ -398,7 +511,7 @@ async fn start_connections_relay<R: Runtime>(window: Window<R>) -> Result<(), St
     // 启动WebSocket连接和数据处理任务
     let _handle = task::spawn(async move {
         let url = Url::parse(&format!("ws://127.0.0.1:{}/connections?token={}", 
-                                     network::DEFAULT_CLASH_API_PORT, token)).unwrap();
+                                     settings_util::clash_api_port(), token)).unwrap();
         
         match connect_async(url).await {
             Ok((ws_stream, _)) => {
@@ -1,95 +1,26 @@
-use crate::entity::config_model;
-use crate::utils::app_util::get_work_dir;
-use crate::utils::config_util::ConfigUtil;
-use std::error::Error;
-use std::path::Path;
+use crate::app::config_service::update_settings_and_regenerate;
 use tracing::info;
-use crate::app::constants::{paths, network, config as config_constants, messages};
+use crate::app::constants::{network, messages};
+use crate::utils::settings_util;
 use serde_json::{json, Value};
 use reqwest::Client;
 use tauri::{Runtime, Emitter};
 
 // 修改代理模式为系统代理
 #[tauri::command]
-pub fn set_system_proxy() -> Result<(), String> {
-    let config_path = paths::get_config_path();
-    let json_util =
-        ConfigUtil::new(config_path.to_str().unwrap()).map_err(|e| format!("{}: {}", messages::ERR_CONFIG_READ_FAILED, e))?;
+pub async fn set_system_proxy() -> Result<(), String> {
+    update_settings_and_regenerate(|settings| settings.tun_enabled = false).await?;
 
-    let mut json_util = json_util;
-    let target_keys = vec!["inbounds"];
-    let new_structs = vec![config_model::Inbound {
-        r#type: config_constants::DEFAULT_INBOUND_TYPE.to_string(),
-        tag: config_constants::DEFAULT_INBOUND_TAG.to_string(),
-        listen: Some(network::DEFAULT_LISTEN_ADDRESS.to_string()),
-        listen_port: Some(network::DEFAULT_PROXY_PORT),
-        address: None,
-        auto_route: None,
-        strict_route: None,
-        stack: None,
-        sniff: None,
-        set_system_proxy: Some(true),
-    }];
-
-    json_util.update_key(target_keys.clone(), serde_json::to_value(new_structs).unwrap());
-    match json_util.save_to_file() {
-        Ok(_) => {
-            info!("{}", messages::INFO_PROXY_MODE_ENABLED);
-            Ok(())
-        }
-        Err(e) => Err(format!("{}: {}", messages::ERR_CONFIG_READ_FAILED, e)),
-    }
+    info!("{}", messages::INFO_PROXY_MODE_ENABLED);
+    Ok(())
 }
 
 // 修改TUN 模式为代理模式
 #[tauri::command]
-pub fn set_tun_proxy() -> Result<(), String> {
-    set_tun_proxy_impl().map_err(|e| format!("设置TUN代理失败: {}", e))
-}
-
-fn set_tun_proxy_impl() -> Result<(), Box<dyn Error>> {
-    let work_dir = get_work_dir();
-    let path = Path::new(&work_dir).join("sing-box/config.json");
-    let mut json_util = ConfigUtil::new(path.to_str().unwrap())?;
-
-    let target_keys = vec!["inbounds"]; // 修改为你的属性路径
-    let new_structs = vec![
-        config_model::Inbound {
-            r#type: "mixed".to_string(),
-            tag: "mixed-in".to_string(),
-            listen: Some("0.0.0.0".to_string()),
-            listen_port: Some(2080),
-            address: None,
-            auto_route: None,
-            strict_route: None,
-            stack: None,
-            sniff: None,
-            set_system_proxy: None,
-        },
-        config_model::Inbound {
-            r#type: "tun".to_string(),
-            tag: "tun-in".to_string(),
-            listen: None,
-            listen_port: None,
-            address: Some(vec![
-                "172.18.0.1/30".to_string(),
-                "fdfe:dcba:9876::1/126".to_string(),
-            ]),
-            auto_route: Some(true),
-            strict_route: Some(true),
-            stack: Some("mixed".to_string()),
-            sniff: None,
-            set_system_proxy: None,
-        },
-    ];
-
-    json_util.modify_property(
-        &target_keys,
-        serde_json::to_value(new_structs).map_err(|e| format!("序列化配置失败: {}", e))?,
-    );
-    json_util
-        .save()
-        .map_err(|e| format!("保存配置文件失败: {}", e))?;
+pub async fn set_tun_proxy() -> Result<(), String> {
+    update_settings_and_regenerate(|settings| settings.tun_enabled = true)
+        .await
+        .map_err(|e| format!("设置TUN代理失败: {}", e))?;
 
     info!("TUN代理模式已设置");
     Ok(())
@@ -97,55 +28,19 @@ fn set_tun_proxy_impl() -> Result<(), Box<dyn Error>> {
 
 // 切换 IPV6版本模式
 #[tauri::command]
-pub fn toggle_ip_version(prefer_ipv6: bool) -> Result<(), String> {
+pub async fn toggle_ip_version(prefer_ipv6: bool) -> Result<(), String> {
     info!(
         "开始切换IP版本模式: {}",
         if prefer_ipv6 { "IPv6优先" } else { "仅IPv4" }
     );
 
-    let work_dir = get_work_dir();
-    let path = Path::new(&work_dir).join("sing-box/config.json");
+    update_settings_and_regenerate(|settings| settings.prefer_ipv6 = prefer_ipv6).await?;
 
-    // 读取文件内容
-    let content = std::fs::read_to_string(&path).map_err(|e| format!("读取配置文件失败: {}", e))?;
-    
-    // 先尝试解析为JSON以验证是否有效
-    let json_config: Value = serde_json::from_str(&content)
-        .map_err(|e| format!("解析配置文件失败: {}", e))?;
-    
-    // 检查DNS配置结构是否存在
-    if !json_config.get("dns").and_then(|dns| dns.get("servers")).is_some() {
-        return Err("配置文件缺少DNS服务器配置".to_string());
-    }
-    
-    // 对服务器配置中的每个策略进行修改
-    let mut modified_content = content;
-    
-    // 识别现有策略和目标策略
-    let current_strategy = if prefer_ipv6 { "ipv4_only" } else { "prefer_ipv6" };
-    let target_strategy = if prefer_ipv6 { "prefer_ipv6" } else { "ipv4_only" };
-    
-    // 替换策略字符串，但确保是在正确的上下文中
-    // 通过查找 "strategy": "current_strategy" 模式来定位
-    let pattern = format!("\"strategy\": \"{}\"", current_strategy);
-    let replacement = format!("\"strategy\": \"{}\"", target_strategy);
-    
-    // 执行替换
-    modified_content = modified_content.replace(&pattern, &replacement);
-    
-    // 验证修改后的内容仍然是有效的JSON
-    let _: Value = serde_json::from_str(&modified_content)
-        .map_err(|e| format!("修改后的配置不是有效的JSON: {}", e))?;
-    
-    // 保存修改后的内容
-    std::fs::write(&path, modified_content)
-        .map_err(|e| format!("保存配置文件失败: {}", e))?;
-    
     info!(
         "IP版本模式已成功切换为: {}",
         if prefer_ipv6 { "IPv6优先" } else { "仅IPv4" }
     );
-    
+
     Ok(())
 }
 
@@ -161,7 +56,7 @@ pub async fn get_proxies() -> Result<Value, String> {
     let token = get_api_token();
     let url = format!("http://{}:{}/proxies?token={}", 
         network::DEFAULT_CLASH_API_ADDRESS, 
-        network::DEFAULT_CLASH_API_PORT,
+        settings_util::clash_api_port(),
         token);
     
     // 创建禁用代理的HTTP客户端
@@ -192,7 +87,7 @@ pub async fn change_proxy(group: String, proxy: String) -> Result<(), String> {
     let token = get_api_token();
     let url = format!("http://{}:{}/proxies/{}?token={}", 
         network::DEFAULT_CLASH_API_ADDRESS, 
-        network::DEFAULT_CLASH_API_PORT, 
+        settings_util::clash_api_port(), 
         group, 
         token);
     
@@ -238,7 +133,7 @@ pub async fn test_group_delay<R: Runtime>(
     // 构建请求URL
     let url = format!("http://{}:{}/group/{}/delay?url={}&timeout=2000&token={}", 
         network::DEFAULT_CLASH_API_ADDRESS, 
-        network::DEFAULT_CLASH_API_PORT,
+        settings_util::clash_api_port(),
         urlencoding::encode(&group),
         urlencoding::encode(&test_url),
         token);
@@ -293,7 +188,7 @@ pub async fn get_version_info() -> Result<Value, String> {
     let token = get_api_token();
     let url = format!("http://{}:{}/version?token={}", 
         network::DEFAULT_CLASH_API_ADDRESS, 
-        network::DEFAULT_CLASH_API_PORT,
+        settings_util::clash_api_port(),
         token);
     
     // 创建禁用代理的HTTP客户端
@@ -324,7 +219,7 @@ pub async fn get_rules() -> Result<Value, String> {
     let token = get_api_token();
     let url = format!("http://{}:{}/rules?token={}", 
         network::DEFAULT_CLASH_API_ADDRESS, 
-        network::DEFAULT_CLASH_API_PORT,
+        settings_util::clash_api_port(),
         token);
     
     // 创建禁用代理的HTTP客户端
@@ -1,32 +1,53 @@
-use crate::entity::config_model::{CacheFileConfig, ClashApiConfig, Config};
-use crate::app::constants::{paths, messages, network};
-use crate::utils::config_util::ConfigUtil;
-use crate::utils::app_util::{get_work_dir, get_template_path};
+use crate::app::config_service;
+use crate::app::constants::{messages, paths, subscription};
+use crate::app::kernel_service::PROCESS_MANAGER;
+use crate::utils::settings_util;
+use crate::utils::share_link_util;
+use crate::utils::node_rule_util;
+use crate::utils::subscription_util;
+use crate::entity::subscription_model::{NodeRules, Subscription, SubscriptionUsage, SubscriptionUserInfo};
+use std::collections::{HashMap, HashSet};
 use std::error::Error;
-use std::fs::File;
-use std::io::{Read, Write};
-use std::path::Path;
-use tracing::{info, error};
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, Runtime};
+use tracing::{info, error, warn};
 use base64;
+use serde::{Deserialize, Serialize};
 use serde_json::{json, Value};
 
-// 下载订阅
-#[tauri::command]
-pub async fn download_subscription(url: String) -> Result<(), String> {
-    download_and_process_subscription(url)
-        .await
-        .map_err(|e| format!("{}: {}", messages::ERR_SUBSCRIPTION_FAILED, e))?;
-    let _ = crate::app::proxy_service::set_system_proxy();
-    Ok(())
+// sing-box 格式订阅中视为节点的出站类型
+const SUPPORTED_NODE_TYPES: [&str; 12] = [
+    "vless", "vmess", "trojan", "shadowsocks", "shadowsocksr", "socks", "http",
+    "hysteria", "hysteria2", "tuic", "wireguard", "ssh",
+];
+
+// 导入时被跳过的节点及原因
+#[derive(Debug, Clone, Serialize)]
+pub struct SkippedNode {
+    pub name: String,
+    pub reason: String,
 }
 
-// 手动添加订阅内容
-#[tauri::command]
-pub async fn add_manual_subscription(content: String) -> Result<(), String> {
-    process_subscription_content(content)
-        .map_err(|e| format!("{}: {}", messages::ERR_PROCESS_SUBSCRIPTION_FAILED, e))?;
-    let _ = crate::app::proxy_service::set_system_proxy();
-    Ok(())
+// 订阅导入结果
+#[derive(Debug, Clone, Serialize)]
+pub struct ImportResult {
+    pub node_count: usize,
+    pub skipped: Vec<SkippedNode>,
+}
+
+// 从订阅内容中提取到的节点
+#[derive(Debug, Default)]
+struct ExtractedNodes {
+    nodes: Vec<Value>,
+    skipped: Vec<SkippedNode>,
+}
+
+// 下载订阅得到的节点以及响应头中的订阅信息
+#[derive(Debug)]
+struct FetchedSubscription {
+    extracted: ExtractedNodes,
+    user_info: Option<SubscriptionUserInfo>,
+    profile_update_interval: Option<u64>,
 }
 
 // 获取当前配置文件内容
@@ -48,24 +69,16 @@ pub fn get_current_config() -> Result<String, String> {
 
 // 切换代理模式（global、rule或tun）
 #[tauri::command]
-pub fn toggle_proxy_mode(mode: String) -> Result<String, String> {
+pub async fn toggle_proxy_mode(mode: String) -> Result<String, String> {
     // 验证模式参数
     if !["global", "rule", "tun"].contains(&mode.as_str()) {
         return Err(format!("无效的代理模式: {}", mode));
     }
     
     info!("正在切换代理模式为: {}", mode);
-    
-    let work_dir = get_work_dir();
-    let path = Path::new(&work_dir).join("sing-box/config.json");
-    
-    // 检查文件是否存在
-    if !path.exists() {
-        return Err("配置文件不存在，请先添加订阅".to_string());
-    }
-    
-    // 修改配置文件
-    match modify_default_mode(&path, mode.clone()) {
+
+    let clash_mode = mode.clone();
+    match config_service::update_settings_and_regenerate(move |settings| settings.clash_mode = clash_mode).await {
         Ok(_) => {
             info!("代理模式已切换为: {}", mode);
             Ok(format!("代理模式已切换为: {}", mode))
@@ -77,48 +90,502 @@ pub fn toggle_proxy_mode(mode: String) -> Result<String, String> {
     }
 }
 
-// 修改配置文件中的default_mode
-fn modify_default_mode(config_path: &Path, mode: String) -> Result<(), Box<dyn Error>> {
-    // 读取现有配置
-    let mut json_util = ConfigUtil::new(config_path.to_str().unwrap())?;
-    
-    // 而是直接创建新的配置并修改
-    let target_keys = vec!["experimental"];
-    
-    // 创建新的配置，设置mode
-    let config = Config {
-        clash_api: ClashApiConfig {
-            external_controller: format!("127.0.0.1:{}", network::DEFAULT_CLASH_API_PORT),
-            external_ui: "metacubexd".to_string(),
-            external_ui_download_url: "".to_string(),
-            external_ui_download_detour: "手动切换".to_string(),
-            default_mode: mode, // 设置为传入的模式
-        },
-        cache_file: CacheFileConfig { enabled: true },
+// 手动添加的订阅使用的名称
+const MANUAL_SUBSCRIPTION_NAME: &str = "手动添加";
+
+// 下载订阅（按URL添加或刷新对应的订阅）
+#[tauri::command]
+pub async fn download_subscription(url: String) -> Result<ImportResult, String> {
+    let url = url.trim().to_string();
+    let existing = subscription_util::load_registry()?
+        .subscriptions
+        .into_iter()
+        .find(|s| s.url.as_deref() == Some(url.as_str()));
+
+    let (id, created) = match existing {
+        Some(subscription) => (subscription.id, false),
+        None => (register_subscription(default_subscription_name(&url)?, Some(url))?.id, true),
     };
-    
-    // 更新配置
-    json_util.modify_property(&target_keys, serde_json::to_value(config)?);
-    json_util.save()?;
-    
+
+    // 新建的订阅首次拉取失败时不保留，已有的订阅保持不变
+    let result = refresh_subscription(id.clone()).await;
+    if result.is_err() && created {
+        discard_subscription(&id);
+    }
+    result
+}
+
+// 手动添加订阅内容
+#[tauri::command]
+pub async fn add_manual_subscription(content: String) -> Result<ImportResult, String> {
+    let ExtractedNodes { nodes, skipped } = extract_nodes_from_subscription(&content)
+        .map_err(|e| format!("{}: {}", messages::ERR_PROCESS_SUBSCRIPTION_FAILED, e))?;
+    if nodes.is_empty() {
+        return Err(format!("{}: 无法从订阅内容提取节点信息", messages::ERR_PROCESS_SUBSCRIPTION_FAILED));
+    }
+
+    // 手动添加的内容统一保存在同一个订阅中
+    let existing = subscription_util::load_registry()?
+        .subscriptions
+        .into_iter()
+        .find(|s| s.url.is_none() && s.name == MANUAL_SUBSCRIPTION_NAME);
+    let id = match existing {
+        Some(subscription) => subscription.id,
+        None => register_subscription(MANUAL_SUBSCRIPTION_NAME.to_string(), None)?.id,
+    };
+
+    store_subscription_nodes(&id, &nodes)?;
+    apply_subscriptions().await?;
+
+    Ok(ImportResult {
+        node_count: nodes.len(),
+        skipped,
+    })
+}
+
+// 获取订阅列表
+#[tauri::command]
+pub fn list_subscriptions() -> Result<Vec<Subscription>, String> {
+    Ok(subscription_util::load_registry()?.subscriptions)
+}
+
+// 添加订阅并立即拉取节点
+#[tauri::command]
+pub async fn add_subscription(name: String, url: String) -> Result<Subscription, String> {
+    let name = name.trim().to_string();
+    if name.is_empty() {
+        return Err("订阅名称不能为空".to_string());
+    }
+
+    let subscription = register_subscription(name, Some(url.trim().to_string()))?;
+
+    // 首次拉取失败时不保留该订阅
+    if let Err(e) = refresh_subscription(subscription.id.clone()).await {
+        discard_subscription(&subscription.id);
+        return Err(e);
+    }
+
+    subscription_util::load_registry()?
+        .find(&subscription.id)
+        .cloned()
+        .ok_or_else(|| "订阅不存在".to_string())
+}
+
+// 删除订阅
+#[tauri::command]
+pub async fn remove_subscription(id: String) -> Result<(), String> {
+    let (index, removed) = subscription_util::update_registry(|registry| {
+        let index = registry
+            .subscriptions
+            .iter()
+            .position(|s| s.id == id)
+            .ok_or("订阅不存在")?;
+        Ok((index, registry.subscriptions.remove(index)))
+    })?;
+
+    // 配置生成失败时恢复订阅，避免配置中残留已删除订阅的节点
+    if let Err(e) = apply_subscriptions().await {
+        let _ = subscription_util::update_registry(|registry| {
+            let index = index.min(registry.subscriptions.len());
+            registry.subscriptions.insert(index, removed);
+            Ok(())
+        });
+        return Err(e);
+    }
+    subscription_util::remove_nodes(&id);
+
+    info!("已删除订阅: {}", id);
     Ok(())
 }
 
-async fn download_and_process_subscription(url: String) -> Result<(), Box<dyn Error>> {
+// 启用或禁用订阅
+#[tauri::command]
+pub async fn set_subscription_enabled(id: String, enabled: bool) -> Result<(), String> {
+    let previous = subscription_util::update_registry(|registry| {
+        let subscription = registry.find_mut(&id).ok_or("订阅不存在")?;
+        let previous = subscription.enabled;
+        subscription.enabled = enabled;
+        Ok(previous)
+    })?;
+
+    // 配置生成失败时恢复启用状态，保持注册表与配置文件一致
+    if let Err(e) = apply_subscriptions().await {
+        let _ = subscription_util::update_registry(|registry| {
+            if let Some(subscription) = registry.find_mut(&id) {
+                subscription.enabled = previous;
+            }
+            Ok(())
+        });
+        return Err(e);
+    }
+
+    info!("订阅 {} 已{}", id, if enabled { "启用" } else { "禁用" });
+    Ok(())
+}
+
+// 重新拉取订阅节点
+#[tauri::command]
+pub async fn refresh_subscription(id: String) -> Result<ImportResult, String> {
+    let result = refresh_subscription_nodes(&id).await?;
+    apply_subscriptions().await?;
+    Ok(result)
+}
+
+// 设置订阅的自动更新间隔(分钟)，为空时关闭自动更新
+#[tauri::command]
+pub fn set_subscription_interval(id: String, interval: Option<u64>) -> Result<(), String> {
+    if let Some(minutes) = interval {
+        if minutes < subscription::MIN_UPDATE_INTERVAL {
+            return Err(format!("自动更新间隔不能小于 {} 分钟", subscription::MIN_UPDATE_INTERVAL));
+        }
+    }
+
+    subscription_util::update_registry(|registry| {
+        let subscription = registry.find_mut(&id).ok_or("订阅不存在")?;
+        if subscription.url.is_none() {
+            return Err("手动添加的订阅无法自动更新".to_string());
+        }
+        subscription.update_interval = interval;
+        Ok(())
+    })
+}
+
+// 设置订阅的节点处理规则
+#[tauri::command]
+pub async fn set_subscription_rules(id: String, rules: NodeRules) -> Result<(), String> {
+    node_rule_util::validate_rules(&rules)?;
+    subscription_util::update_registry(|registry| {
+        let subscription = registry.find_mut(&id).ok_or("订阅不存在")?;
+        subscription.rules = rules;
+        Ok(())
+    })?;
+
+    info!("订阅 {} 的节点规则已更新", id);
+    apply_subscriptions().await
+}
+
+// 预览节点规则的处理结果，不修改订阅和配置
+#[tauri::command]
+pub fn preview_subscription_rules(id: String, rules: Option<NodeRules>) -> Result<Vec<Value>, String> {
+    let subscription = subscription_util::load_registry()?
+        .find(&id)
+        .cloned()
+        .ok_or_else(|| "订阅不存在".to_string())?;
+    let rules = rules.unwrap_or(subscription.rules);
+
+    node_rule_util::apply_node_rules(subscription_util::load_nodes(&id), &rules)
+}
+
+// 获取订阅的流量和到期信息
+#[tauri::command]
+pub fn get_subscription_usage(id: String) -> Result<SubscriptionUsage, String> {
+    let subscription = subscription_util::load_registry()?
+        .find(&id)
+        .cloned()
+        .ok_or_else(|| "订阅不存在".to_string())?;
+    let info = subscription
+        .user_info
+        .ok_or_else(|| "订阅未提供流量信息".to_string())?;
+
+    let used = info.used();
+    let remaining = info.remaining();
+    let quota_warning = info.total > 0
+        && used.saturating_mul(100) >= info.total.saturating_mul(subscription::QUOTA_WARNING_PERCENT);
+
+    let now = subscription_util::now_timestamp() as i64;
+    let expires_in = info.expire.map(|expire| expire as i64 - now);
+    let expire_warning = expires_in
+        .map(|secs| secs < (subscription::EXPIRE_WARNING_DAYS * 24 * 3600) as i64)
+        .unwrap_or(false);
+
+    Ok(SubscriptionUsage {
+        id: subscription.id,
+        name: subscription.name,
+        upload: info.upload,
+        download: info.download,
+        used,
+        total: info.total,
+        remaining,
+        expire: info.expire,
+        expires_in,
+        profile_update_interval: subscription.profile_update_interval,
+        quota_warning,
+        expire_warning,
+    })
+}
+
+// 拉取订阅节点并保存，失败原因记录在订阅中
+async fn refresh_subscription_nodes(id: &str) -> Result<ImportResult, String> {
+    let subscription = subscription_util::load_registry()?
+        .find(id)
+        .cloned()
+        .ok_or_else(|| "订阅不存在".to_string())?;
+    let url = subscription.url.ok_or_else(|| "手动添加的订阅无法刷新".to_string())?;
+
+    let fetched = match fetch_subscription_nodes(&url).await {
+        Ok(fetched) => fetched,
+        Err(e) => {
+            let message = format!("{}: {}", messages::ERR_SUBSCRIPTION_FAILED, e);
+            let _ = subscription_util::update_registry(|registry| {
+                if let Some(subscription) = registry.find_mut(id) {
+                    subscription.last_error = Some(message.clone());
+                }
+                Ok(())
+            });
+            return Err(message);
+        }
+    };
+
+    let ExtractedNodes { nodes, skipped } = fetched.extracted;
+    store_subscription_nodes(id, &nodes)?;
+    subscription_util::update_registry(|registry| {
+        let subscription = registry.find_mut(id).ok_or("订阅不存在")?;
+        subscription.user_info = fetched.user_info;
+        subscription.profile_update_interval = fetched.profile_update_interval;
+        Ok(())
+    })?;
+
+    info!("订阅 {} 已刷新，共 {} 个节点", subscription.name, nodes.len());
+    Ok(ImportResult {
+        node_count: nodes.len(),
+        skipped,
+    })
+}
+
+// 启动订阅自动更新调度器
+pub fn start_subscription_scheduler<R: Runtime>(app: AppHandle<R>) {
+    tauri::async_runtime::spawn(async move {
+        let mut ticker = tokio::time::interval(Duration::from_secs(subscription::SCHEDULER_TICK_SECS));
+        // 记录每个订阅最近一次自动更新的时间，用于失败后的重试间隔
+        let mut last_attempts: HashMap<String, u64> = HashMap::new();
+
+        loop {
+            ticker.tick().await;
+            run_scheduled_refresh(&app, &mut last_attempts).await;
+        }
+    });
+    info!("订阅自动更新调度器已启动");
+}
+
+// 更新所有到期的订阅，有订阅更新成功时重新生成配置并热重启内核
+async fn run_scheduled_refresh<R: Runtime>(app: &AppHandle<R>, last_attempts: &mut HashMap<String, u64>) {
+    let now = subscription_util::now_timestamp();
+    let registry = match subscription_util::load_registry() {
+        Ok(registry) => registry,
+        Err(e) => {
+            error!("{}: {}", messages::ERR_PROCESS_SUBSCRIPTION_FAILED, e);
+            return;
+        }
+    };
+    let due: Vec<Subscription> = registry
+        .subscriptions
+        .into_iter()
+        .filter(|s| is_subscription_due(s, now, last_attempts.get(&s.id).copied()))
+        .collect();
+    if due.is_empty() {
+        return;
+    }
+
+    let mut updated = 0;
+    for subscription in due {
+        last_attempts.insert(subscription.id.clone(), now);
+        match refresh_subscription_nodes(&subscription.id).await {
+            Ok(result) => {
+                updated += 1;
+                let _ = app.emit(
+                    "subscription-updated",
+                    json!({
+                        "id": subscription.id,
+                        "name": subscription.name,
+                        "node_count": result.node_count
+                    }),
+                );
+            }
+            Err(e) => {
+                warn!("订阅 {} 自动更新失败: {}", subscription.name, e);
+                let _ = app.emit(
+                    "subscription-update-failed",
+                    json!({
+                        "id": subscription.id,
+                        "name": subscription.name,
+                        "error": e
+                    }),
+                );
+            }
+        }
+    }
+
+    if updated == 0 {
+        return;
+    }
+
+    if let Err(e) = config_service::regenerate_config().await {
+        error!("{}: {}", messages::ERR_PROCESS_SUBSCRIPTION_FAILED, e);
+        return;
+    }
+
+    // 内核运行中时重启以加载新的节点
+    if PROCESS_MANAGER.is_running().await {
+        match PROCESS_MANAGER.restart().await {
+            Ok(_) => info!("订阅已自动更新，内核已重启"),
+            Err(e) => error!("订阅自动更新后重启内核失败: {}", e),
+        }
+    }
+}
+
+// 判断订阅是否需要自动更新
+fn is_subscription_due(subscription: &Subscription, now: u64, last_attempt: Option<u64>) -> bool {
+    if !subscription.enabled || subscription.url.is_none() {
+        return false;
+    }
+    // 未手动设置间隔时使用服务商建议的更新间隔
+    let interval = match subscription
+        .update_interval
+        .or(subscription.profile_update_interval.map(|hours| hours.saturating_mul(60)))
+    {
+        Some(minutes) => minutes.max(subscription::MIN_UPDATE_INTERVAL).saturating_mul(60),
+        None => return false,
+    };
+
+    // 更新失败后按重试间隔再次尝试，避免每次检查都重新请求
+    if let Some(attempt) = last_attempt {
+        if now.saturating_sub(attempt) < interval.min(subscription::RETRY_INTERVAL * 60) {
+            return false;
+        }
+    }
+
+    match subscription.last_updated {
+        Some(updated) => now.saturating_sub(updated) >= interval,
+        None => true,
+    }
+}
+
+// 在注册表中登记新的订阅
+fn register_subscription(name: String, url: Option<String>) -> Result<Subscription, String> {
+    subscription_util::update_registry(|registry| {
+        if registry.subscriptions.iter().any(|s| s.name == name) {
+            return Err(format!("订阅名称已存在: {}", name));
+        }
+
+        let subscription = Subscription {
+            id: subscription_util::generate_subscription_id(),
+            name,
+            url,
+            enabled: true,
+            last_updated: None,
+            node_count: 0,
+            update_interval: None,
+            last_error: None,
+            user_info: None,
+            profile_update_interval: None,
+            rules: NodeRules::default(),
+        };
+        registry.subscriptions.push(subscription.clone());
+        Ok(subscription)
+    })
+}
+
+// 移除首次拉取失败的订阅，节点已保存但配置生成失败时一并删除节点缓存
+fn discard_subscription(id: &str) {
+    let _ = subscription_util::update_registry(|registry| {
+        registry.subscriptions.retain(|s| s.id != id);
+        Ok(())
+    });
+    subscription_util::remove_nodes(id);
+}
+
+// 保存订阅节点并更新注册表中的统计信息
+fn store_subscription_nodes(id: &str, nodes: &[Value]) -> Result<(), String> {
+    subscription_util::save_nodes(id, nodes)?;
+    subscription_util::update_registry(|registry| {
+        let subscription = registry.find_mut(id).ok_or("订阅不存在")?;
+        subscription.last_updated = Some(subscription_util::now_timestamp());
+        subscription.node_count = nodes.len();
+        subscription.last_error = None;
+        Ok(())
+    })
+}
+
+// 根据订阅URL生成默认名称，与已有订阅重名时追加序号
+fn default_subscription_name(url: &str) -> Result<String, String> {
+    let base = url::Url::parse(url)
+        .ok()
+        .and_then(|u| u.host_str().map(|h| h.to_string()))
+        .unwrap_or_else(|| "订阅".to_string());
+
+    let registry = subscription_util::load_registry()?;
+    let mut name = base.clone();
+    let mut index = 2;
+    while registry.subscriptions.iter().any(|s| s.name == name) {
+        name = format!("{} {}", base, index);
+        index += 1;
+    }
+    Ok(name)
+}
+
+// 订阅变化后重新生成配置
+async fn apply_subscriptions() -> Result<(), String> {
+    config_service::regenerate_config().await.map_err(|e| format!("{}: {}", messages::ERR_PROCESS_SUBSCRIPTION_FAILED, e))?;
+    Ok(())
+}
+
+// 合并所有启用订阅的节点，返回启用的订阅数量和节点列表
+pub(crate) fn collect_enabled_nodes() -> Result<(usize, Vec<Value>), String> {
+    let registry = subscription_util::load_registry()?;
+
+    let mut used_tags = HashSet::new();
+    let mut nodes = Vec::new();
+    let mut enabled_count = 0;
+    for subscription in registry.subscriptions.iter().filter(|s| s.enabled) {
+        enabled_count += 1;
+        let subscription_nodes = subscription_util::load_nodes(&subscription.id);
+        let subscription_nodes = match node_rule_util::validate_rules(&subscription.rules) {
+            Ok(_) => node_rule_util::apply_node_rules(subscription_nodes, &subscription.rules).unwrap_or_default(),
+            Err(e) => {
+                warn!("订阅 {} 的节点规则无效，已忽略: {}", subscription.name, e);
+                subscription_nodes
+            }
+        };
+        nodes.extend(subscription_util::prefix_node_tags(
+            &subscription.name,
+            subscription_nodes,
+            &mut used_tags,
+        ));
+    }
+
+    Ok((enabled_count, nodes))
+}
+
+// 下载订阅并提取节点
+async fn fetch_subscription_nodes(url: &str) -> Result<FetchedSubscription, Box<dyn Error>> {
     let client = reqwest::Client::new();
     let mut headers = reqwest::header::HeaderMap::new();
     let user_agent = reqwest::header::HeaderValue::from_static("sing-box-windows/1.0 (sing-box; compatible; Windows NT 10.0)");
     headers.insert(reqwest::header::USER_AGENT, user_agent);
 
     let response = client.get(url.trim()).headers(headers).send().await?;
+
+    // 读取响应头中的流量、到期时间和建议更新间隔
+    let header_value = |name: &str| {
+        response
+            .headers()
+            .get(name)
+            .and_then(|v| v.to_str().ok())
+            .map(|v| v.to_string())
+    };
+    let user_info = header_value("subscription-userinfo")
+        .and_then(|v| subscription_util::parse_subscription_userinfo(&v));
+    let profile_update_interval = header_value("profile-update-interval")
+        .and_then(|v| subscription_util::parse_profile_update_interval(&v));
+
     let response_text = response.text().await?;
     
     // 直接尝试从原始内容提取节点
-    let mut extracted_nodes = extract_nodes_from_subscription(&response_text)?;
-    info!("从原始内容提取到 {} 个节点", extracted_nodes.len());
+    let mut extracted = extract_nodes_from_subscription(&response_text)?;
+    info!("从原始内容提取到 {} 个节点", extracted.nodes.len());
     
     // 如果没有提取到节点，尝试base64解码后重新提取
-    if extracted_nodes.is_empty() {
+    if extracted.nodes.is_empty() {
         info!("未从原始内容提取到节点，尝试base64解码...");
         
         // 尝试标准base64解码
@@ -126,8 +593,8 @@ async fn download_and_process_subscription(url: String) -> Result<(), Box<dyn Er
         if let Ok(decoded) = decoded_result {
             if let Ok(decoded_text) = String::from_utf8(decoded.clone()) {
                 info!("base64标准解码成功，重新从解码内容提取节点...");
-                extracted_nodes = extract_nodes_from_subscription(&decoded_text)?;
-                info!("从标准base64解码内容提取到 {} 个节点", extracted_nodes.len());
+                extracted = extract_nodes_from_subscription(&decoded_text)?;
+                info!("从标准base64解码内容提取到 {} 个节点", extracted.nodes.len());
             } else {
                 info!("base64解码成功但无法转换为UTF-8文本");
             }
@@ -139,8 +606,8 @@ async fn download_and_process_subscription(url: String) -> Result<(), Box<dyn Er
             if let Ok(decoded) = url_safe_decoded {
                 if let Ok(decoded_text) = String::from_utf8(decoded.clone()) {
                     info!("URL安全base64解码成功，重新从解码内容提取节点...");
-                    extracted_nodes = extract_nodes_from_subscription(&decoded_text)?;
-                    info!("从URL安全base64解码内容提取到 {} 个节点", extracted_nodes.len());
+                    extracted = extract_nodes_from_subscription(&decoded_text)?;
+                    info!("从URL安全base64解码内容提取到 {} 个节点", extracted.nodes.len());
                 } else {
                     info!("URL安全base64解码成功但无法转换为UTF-8文本");
                 }
@@ -151,7 +618,7 @@ async fn download_and_process_subscription(url: String) -> Result<(), Box<dyn Er
     }
     
     // 如果依然没有提取到节点，再尝试移除可能的前缀后再解码
-    if extracted_nodes.is_empty() {
+    if extracted.nodes.is_empty() {
         info!("标准解码方法均未提取到节点，尝试移除前缀后再解码...");
         
         // 移除可能的前缀 (例如: "ss://", "vmess://")
@@ -160,93 +627,28 @@ async fn download_and_process_subscription(url: String) -> Result<(), Box<dyn Er
         
         if let Ok(decoded) = base64::decode(&stripped_text) {
             if let Ok(decoded_text) = String::from_utf8(decoded) {
-                extracted_nodes = extract_nodes_from_subscription(&decoded_text)?;
-                info!("从移除前缀后解码内容提取到 {} 个节点", extracted_nodes.len());
+                extracted = extract_nodes_from_subscription(&decoded_text)?;
+                info!("从移除前缀后解码内容提取到 {} 个节点", extracted.nodes.len());
             }
         }
     }
     
     // 如果依然没有提取到节点，返回错误
-    if extracted_nodes.is_empty() {
+    if extracted.nodes.is_empty() {
         error!("无法从订阅内容提取节点信息，已尝试所有解码方式");
         return Err("无法从订阅内容提取节点信息，请检查订阅链接或内容格式".into());
     }
 
-    info!("成功提取到 {} 个节点，准备应用到配置", extracted_nodes.len());
-    
-    // 使用模板和提取的节点信息创建新的配置
-    let work_dir = get_work_dir();
-    let config_path = Path::new(&work_dir).join("sing-box/config.json");
-    let dir = Path::new(&work_dir).join("sing-box");
-    // 确保目录存在
-    if let Err(e) = std::fs::create_dir_all(&dir) {
-        error!("{}: {}", messages::ERR_CREATE_DIR_FAILED, e);
-    }
-
-    // 读取模板文件
-    let template_path = get_template_path();
-    let mut template_file = File::open(&template_path)?;
-    let mut template_content = String::new();
-    template_file.read_to_string(&mut template_content)?;
-    
-    // 将模板内容解析为JSON对象
-    let mut config: Value = serde_json::from_str(&template_content)?;
-    
-    // 将提取的节点添加到模板配置中
-    if let Some(config_obj) = config.as_object_mut() {
-        if let Some(outbounds) = config_obj.get_mut("outbounds") {
-            if let Some(outbounds_array) = outbounds.as_array_mut() {
-                // 找到"自动选择"出站
-                if let Some(auto_select) = outbounds_array.iter_mut().find(|o| {
-                    o.get("tag").and_then(|t| t.as_str()) == Some("自动选择")
-                }) {
-                    // 更新自动选择的outbounds列表
-                    if let Some(outbound_tags) = auto_select.get_mut("outbounds") {
-                        // 设置所有节点的标签列表
-                        let node_tags: Vec<Value> = extracted_nodes.iter()
-                            .map(|node| json!(node.get("tag").unwrap().as_str().unwrap()))
-                            .collect();
-                        *outbound_tags = json!(node_tags);
-                    }
-                }
-                
-                // 找到"手动切换"出站
-                if let Some(proxy_select) = outbounds_array.iter_mut().find(|o| {
-                    o.get("tag").and_then(|t| t.as_str()) == Some("手动切换")
-                }) {
-                    // 更新手动切换的outbounds列表
-                    if let Some(outbound_tags) = proxy_select.get_mut("outbounds") {
-                        let mut tags = vec![json!("自动选择")];
-                        // 添加所有节点标签
-                        for node in &extracted_nodes {
-                            tags.push(json!(node.get("tag").unwrap().as_str().unwrap()));
-                        }
-                        *outbound_tags = json!(tags);
-                    }
-                }
-                
-                // 更新其他选择器的outbounds列表
-                update_selector_outbounds(outbounds_array, &extracted_nodes);
-                
-                // 将节点添加到outbounds数组末尾
-                for node in extracted_nodes {
-                    outbounds_array.push(node);
-                }
-            }
-        }
-    }
-    
-    // 保存配置到文件
-    let mut config_file = File::create(config_path)?;
-    let config_str = serde_json::to_string_pretty(&config)?;
-    config_file.write_all(config_str.as_bytes())?;
-
-    info!("订阅已更新并应用到模板，配置已保存");
-    Ok(())
+    info!("成功提取到 {} 个节点", extracted.nodes.len());
+    Ok(FetchedSubscription {
+        extracted,
+        user_info,
+        profile_update_interval,
+    })
 }
 
 // 从订阅内容中提取节点信息
-fn extract_nodes_from_subscription(content: &str) -> Result<Vec<Value>, Box<dyn Error>> {
+fn extract_nodes_from_subscription(content: &str) -> Result<ExtractedNodes, Box<dyn Error>> {
     // 清理内容中的非法字符
     let cleaned_content = clean_json_content(content);
     
@@ -254,6 +656,7 @@ fn extract_nodes_from_subscription(content: &str) -> Result<Vec<Value>, Box<dyn
     let content_json: Result<Value, _> = serde_json::from_str(&cleaned_content);
     
     let mut nodes = Vec::new();
+    let mut skipped = Vec::new();
     
     match content_json {
         Ok(json) => {
@@ -286,8 +689,7 @@ fn extract_nodes_from_subscription(content: &str) -> Result<Vec<Value>, Box<dyn
                     };
                     
                     match outbound_type {
-                        Some("vless") | Some("vmess") | Some("trojan") | Some("shadowsocks") | 
-                        Some("shadowsocksr") | Some("socks") | Some("http") => {
+                        Some(t) if SUPPORTED_NODE_TYPES.contains(&t) => {
                             nodes.push(node_with_tag);
                         },
                         _ => {} // 忽略其他类型的出站
@@ -306,7 +708,7 @@ fn extract_nodes_from_subscription(content: &str) -> Result<Vec<Value>, Box<dyn
                                     if let Some(actual_node) = find_outbound_by_tag(&outbounds, sub_tag) {
                                         let node_type = actual_node.get("type").and_then(|t| t.as_str());
                                         if let Some(type_str) = node_type {
-                                            if ["vless", "vmess", "trojan", "shadowsocks", "shadowsocksr", "socks", "http"].contains(&type_str) {
+                                            if SUPPORTED_NODE_TYPES.contains(&type_str) {
                                                 // 确保节点有tag
                                                 let node_with_tag = if actual_node.get("tag").is_none() {
                                                     let mut node_obj = actual_node.clone();
@@ -331,9 +733,7 @@ fn extract_nodes_from_subscription(content: &str) -> Result<Vec<Value>, Box<dyn
                 
                 // 从Clash格式的配置中提取节点并转换为sing-box格式
                 for proxy in proxies {
-                    if let Some(converted_node) = convert_clash_node_to_singbox(proxy) {
-                        nodes.push(converted_node);
-                    }
+                    convert_clash_proxy(proxy, &mut nodes, &mut skipped);
                 }
             } else {
                 // 尝试查找其他可能的位置
@@ -359,7 +759,7 @@ fn extract_nodes_from_subscription(content: &str) -> Result<Vec<Value>, Box<dyn
                                         let item_type = item.get("type").and_then(|t| t.as_str());
                                         
                                         if let Some(t) = item_type {
-                                            if ["vless", "vmess", "trojan", "shadowsocks", "shadowsocksr", "socks", "http"].contains(&t) {
+                                            if SUPPORTED_NODE_TYPES.contains(&t) {
                                                 // 确保节点有tag
                                                 let node_with_tag = if !has_tag {
                                                     let server = item.get("server").and_then(|s| s.as_str()).unwrap_or("unknown");
@@ -387,18 +787,21 @@ fn extract_nodes_from_subscription(content: &str) -> Result<Vec<Value>, Box<dyn
         Err(e) => {
             info!("内容不是有效的JSON格式: {}", e);
             
-            // 尝试解析为Clash YAML格式（简化处理，实际中可能需要更复杂的YAML解析）
-            if cleaned_content.contains("proxies:") {
+            // 尝试解析为Clash YAML格式
+            if content.contains("proxies:") {
                 info!("检测到可能的Clash YAML格式");
-                // 这里应该添加YAML格式解析逻辑，简化实现
-                // 实际中需要使用yaml解析库提取节点并转换
+                extract_nodes_from_clash_yaml(content, &mut nodes, &mut skipped);
             }
             
-            // 检查是否包含URI格式的节点
-            if cleaned_content.contains("vmess://") || cleaned_content.contains("ss://") || 
-               cleaned_content.contains("trojan://") || cleaned_content.contains("vless://") {
+            // 检查是否包含URI格式的节点（使用原始内容，清理后的内容会破坏链接中的特殊字符）
+            if share_link_util::contains_share_links(content) {
                 info!("检测到可能包含URI格式的节点");
-                // TODO: 解析URI格式的节点
+                let (link_nodes, invalid) = share_link_util::parse_share_links(content);
+                nodes.extend(link_nodes);
+                skipped.extend(invalid.into_iter().map(|(link, reason)| SkippedNode {
+                    name: share_link_name(&link),
+                    reason,
+                }));
             }
         }
     }
@@ -423,8 +826,71 @@ fn extract_nodes_from_subscription(content: &str) -> Result<Vec<Value>, Box<dyn
         }
     }
     
-    info!("从订阅中提取了 {} 个节点", fixed_nodes.len());
-    Ok(fixed_nodes)
+    info!("从订阅中提取了 {} 个节点，跳过 {} 个", fixed_nodes.len(), skipped.len());
+    Ok(ExtractedNodes {
+        nodes: fixed_nodes,
+        skipped,
+    })
+}
+
+// 获取分享链接中的节点名称，没有名称时使用链接协议
+fn share_link_name(link: &str) -> String {
+    match link.trim().rsplit_once('#') {
+        Some((_, name)) if !name.is_empty() => urlencoding::decode(name)
+            .map(|n| n.into_owned())
+            .unwrap_or_else(|_| name.to_string()),
+        _ => link.split("://").next().unwrap_or("unknown").to_string(),
+    }
+}
+
+// Clash / Clash.Meta 配置文件中与节点相关的部分
+#[derive(Debug, Deserialize)]
+struct ClashProfile {
+    #[serde(default)]
+    proxies: Vec<serde_yaml::Value>,
+}
+
+// 从Clash YAML配置中提取节点并转换为sing-box格式
+fn extract_nodes_from_clash_yaml(content: &str, nodes: &mut Vec<Value>, skipped: &mut Vec<SkippedNode>) {
+    let content = content.trim_start_matches('\u{FEFF}');
+    let profile: ClashProfile = match serde_yaml::from_str(content) {
+        Ok(profile) => profile,
+        Err(e) => {
+            error!("解析Clash YAML失败: {}", e);
+            return;
+        }
+    };
+
+    info!("检测到Clash YAML格式，proxies数组长度: {}", profile.proxies.len());
+
+    for proxy in profile.proxies {
+        // 转为JSON后复用Clash节点转换逻辑
+        match serde_json::to_value(&proxy) {
+            Ok(proxy) => convert_clash_proxy(&proxy, nodes, skipped),
+            Err(e) => {
+                warn!("跳过无法识别的Clash节点: {}", e);
+                skipped.push(SkippedNode {
+                    name: "unknown".to_string(),
+                    reason: format!("无法识别的节点格式: {}", e),
+                });
+            }
+        }
+    }
+}
+
+// 转换单个Clash节点，失败时记录跳过原因
+fn convert_clash_proxy(proxy: &Value, nodes: &mut Vec<Value>, skipped: &mut Vec<SkippedNode>) {
+    match convert_clash_node_to_singbox(proxy) {
+        Ok(node) => nodes.push(node),
+        Err(reason) => {
+            let name = proxy.get("name").and_then(|n| n.as_str()).unwrap_or("unknown");
+            warn!("跳过Clash节点 {}: {}", name, reason);
+            skipped.push(SkippedNode {
+                name: name.to_string(),
+                reason,
+            });
+        }
+    }
 }
 
 // 清理JSON内容中的非法字符
@@ -530,83 +996,33 @@ fn find_outbound_by_tag<'a>(outbounds: &'a [Value], tag: &str) -> Option<&'a Val
     None
 }
 
-// 将Clash格式的节点转换为sing-box格式
-fn convert_clash_node_to_singbox(clash_node: &Value) -> Option<Value> {
-    // 获取节点类型
-    let node_type = clash_node.get("type").and_then(|t| t.as_str())?;
-    let name = clash_node.get("name").and_then(|n| n.as_str())?;
-    let server = clash_node.get("server").and_then(|s| s.as_str())?;
-    let port = clash_node.get("port").and_then(|p| p.as_u64())?;
-    
+// 将Clash格式的节点转换为sing-box格式，无法转换时返回原因
+fn convert_clash_node_to_singbox(clash_node: &Value) -> Result<Value, String> {
+    // 获取节点基础信息
+    let node_type = clash_str(clash_node, "type").ok_or("缺少节点类型")?;
+    let name = clash_str(clash_node, "name").ok_or("缺少节点名称")?;
+    let server = clash_str(clash_node, "server").ok_or("缺少服务器地址")?;
+    let port = clash_u64(clash_node, "port").ok_or("缺少端口")?;
+
     // 根据不同类型转换节点
-    match node_type {
+    let node = match node_type.as_str() {
         "vmess" => {
-            let uuid = clash_node.get("uuid").and_then(|u| u.as_str())?;
+            let uuid = clash_str(clash_node, "uuid").ok_or("缺少UUID")?;
             let mut node = json!({
                 "tag": name,
                 "type": "vmess",
                 "server": server,
                 "server_port": port,
                 "uuid": uuid,
-                "security": clash_node.get("cipher").and_then(|c| c.as_str()).unwrap_or("auto"),
-                "alter_id": clash_node.get("alterId").and_then(|a| a.as_u64()).unwrap_or(0)
+                "security": clash_str(clash_node, "cipher").unwrap_or_else(|| "auto".to_string()),
+                "alter_id": clash_u64(clash_node, "alterId").unwrap_or(0)
             });
-            
-            // 处理TLS设置
-            if let Some(true) = clash_node.get("tls").and_then(|t| t.as_bool()) {
-                let mut tls = json!({
-                    "enabled": true
-                });
-                
-                if let Some(sni) = clash_node.get("servername").and_then(|s| s.as_str()) {
-                    tls["server_name"] = json!(sni);
-                }
-                
-                if let Some(obj) = tls.as_object_mut() {
-                    obj.insert("utls".to_string(), json!({
-                        "enabled": true,
-                        "fingerprint": "chrome"
-                    }));
-                }
-                
-                if let Some(obj) = node.as_object_mut() {
-                    obj.insert("tls".to_string(), tls);
-                }
-            }
-            
-            // 处理传输方式
-            if let Some(network) = clash_node.get("network").and_then(|n| n.as_str()) {
-                match network {
-                    "ws" => {
-                        let mut transport = json!({
-                            "type": "ws"
-                        });
-                        
-                        if let Some(ws_opts) = clash_node.get("ws-opts") {
-                            if let Some(path) = ws_opts.get("path").and_then(|p| p.as_str()) {
-                                transport["path"] = json!(path);
-                            }
-                            
-                            if let Some(headers) = ws_opts.get("headers") {
-                                if let Some(obj) = headers.as_object() {
-                                    transport["headers"] = json!(obj);
-                                }
-                            }
-                        }
-                        
-                        if let Some(obj) = node.as_object_mut() {
-                            obj.insert("transport".to_string(), transport);
-                        }
-                    },
-                    // 其他传输方式如grpc, http等可以类似处理
-                    _ => {}
-                }
-            }
-            
-            Some(node)
+            apply_clash_tls(&mut node, clash_node, false, true);
+            apply_clash_transport(&mut node, clash_node)?;
+            node
         },
         "vless" => {
-            let uuid = clash_node.get("uuid").and_then(|u| u.as_str())?;
+            let uuid = clash_str(clash_node, "uuid").ok_or("缺少UUID")?;
             let mut node = json!({
                 "tag": name,
                 "type": "vless",
@@ -615,62 +1031,15 @@ fn convert_clash_node_to_singbox(clash_node: &Value) -> Option<Value> {
                 "uuid": uuid,
                 "packet_encoding": "xudp"
             });
-            
-            // 处理TLS设置
-            if let Some(true) = clash_node.get("tls").and_then(|t| t.as_bool()) {
-                let mut tls = json!({
-                    "enabled": true
-                });
-                
-                if let Some(sni) = clash_node.get("servername").and_then(|s| s.as_str()) {
-                    tls["server_name"] = json!(sni);
-                }
-                
-                if let Some(obj) = tls.as_object_mut() {
-                    obj.insert("utls".to_string(), json!({
-                        "enabled": true,
-                        "fingerprint": "chrome"
-                    }));
-                }
-                
-                if let Some(obj) = node.as_object_mut() {
-                    obj.insert("tls".to_string(), tls);
-                }
+            if let Some(flow) = clash_str(clash_node, "flow").filter(|f| !f.is_empty()) {
+                node["flow"] = json!(flow);
             }
-            
-            // 处理传输方式
-            if let Some(network) = clash_node.get("network").and_then(|n| n.as_str()) {
-                match network {
-                    "ws" => {
-                        let mut transport = json!({
-                            "type": "ws"
-                        });
-                        
-                        if let Some(ws_opts) = clash_node.get("ws-opts") {
-                            if let Some(path) = ws_opts.get("path").and_then(|p| p.as_str()) {
-                                transport["path"] = json!(path);
-                            }
-                            
-                            if let Some(headers) = ws_opts.get("headers") {
-                                if let Some(obj) = headers.as_object() {
-                                    transport["headers"] = json!(obj);
-                                }
-                            }
-                        }
-                        
-                        if let Some(obj) = node.as_object_mut() {
-                            obj.insert("transport".to_string(), transport);
-                        }
-                    },
-                    // 其他传输方式
-                    _ => {}
-                }
-            }
-            
-            Some(node)
+            apply_clash_tls(&mut node, clash_node, false, true);
+            apply_clash_transport(&mut node, clash_node)?;
+            node
         },
         "trojan" => {
-            let password = clash_node.get("password").and_then(|p| p.as_str())?;
+            let password = clash_str(clash_node, "password").ok_or("缺少密码")?;
             let mut node = json!({
                 "tag": name,
                 "type": "trojan",
@@ -678,191 +1047,477 @@ fn convert_clash_node_to_singbox(clash_node: &Value) -> Option<Value> {
                 "server_port": port,
                 "password": password
             });
-            
-            // 添加TLS设置（Trojan必须启用TLS）
-            let mut tls = json!({
-                "enabled": true
+            // Trojan必须启用TLS
+            apply_clash_tls(&mut node, clash_node, true, true);
+            apply_clash_transport(&mut node, clash_node)?;
+            node
+        },
+        "ss" | "shadowsocks" => {
+            let password = clash_str(clash_node, "password").ok_or("缺少密码")?;
+            let method = clash_str(clash_node, "cipher").ok_or("缺少加密方式")?;
+            let mut node = json!({
+                "tag": name,
+                "type": "shadowsocks",
+                "server": server,
+                "server_port": port,
+                "method": method,
+                "password": password
             });
-            
-            if let Some(sni) = clash_node.get("sni").and_then(|s| s.as_str()) {
-                tls["server_name"] = json!(sni);
+            apply_clash_ss_plugin(&mut node, clash_node)?;
+            if clash_bool(clash_node, "udp-over-tcp") == Some(true) {
+                node["udp_over_tcp"] = json!(true);
             }
-            
-            if let Some(obj) = tls.as_object_mut() {
-                obj.insert("utls".to_string(), json!({
-                    "enabled": true,
-                    "fingerprint": "chrome"
-                }));
+            node
+        },
+        "ssr" => return Err("sing-box已不再支持ShadowsocksR".to_string()),
+        "hysteria" => {
+            let mut node = json!({
+                "tag": name,
+                "type": "hysteria",
+                "server": server,
+                "server_port": port
+            });
+            if let Some(auth) = clash_str(clash_node, "auth-str").or_else(|| clash_str(clash_node, "auth_str")) {
+                node["auth_str"] = json!(auth);
             }
-            
-            if let Some(obj) = node.as_object_mut() {
-                obj.insert("tls".to_string(), tls);
+            if let Some(obfs) = clash_str(clash_node, "obfs").filter(|o| !o.is_empty()) {
+                node["obfs"] = json!(obfs);
             }
-            
-            Some(node)
+            if let Some(protocol) = clash_str(clash_node, "protocol") {
+                if protocol != "udp" {
+                    return Err(format!("不支持的hysteria协议: {}", protocol));
+                }
+            }
+            node["up_mbps"] = json!(clash_bandwidth(clash_node, "up").ok_or("缺少上行带宽或单位无法识别")?);
+            node["down_mbps"] = json!(clash_bandwidth(clash_node, "down").ok_or("缺少下行带宽或单位无法识别")?);
+            apply_clash_tls(&mut node, clash_node, true, false);
+            node
         },
-        "shadowsocks" => {
-            let password = clash_node.get("password").and_then(|p| p.as_str())?;
-            let method = clash_node.get("cipher").and_then(|c| c.as_str())?;
-            
-            Some(json!({
+        "hysteria2" => {
+            let mut node = json!({
                 "tag": name,
-                "type": "shadowsocks",
+                "type": "hysteria2",
                 "server": server,
                 "server_port": port,
-                "method": method,
-                "password": password
-            }))
+                "password": clash_str(clash_node, "password").unwrap_or_default()
+            });
+            if let Some(obfs) = clash_str(clash_node, "obfs").filter(|o| !o.is_empty()) {
+                node["obfs"] = json!({
+                    "type": obfs,
+                    "password": clash_str(clash_node, "obfs-password").unwrap_or_default()
+                });
+            }
+            if let Some(up) = clash_bandwidth(clash_node, "up") {
+                node["up_mbps"] = json!(up);
+            }
+            if let Some(down) = clash_bandwidth(clash_node, "down") {
+                node["down_mbps"] = json!(down);
+            }
+            apply_clash_tls(&mut node, clash_node, true, false);
+            node
         },
-        // 其他类型可以类似处理
-        _ => None
-    }
-}
+        "tuic" => {
+            if clash_node.get("token").is_some() {
+                return Err("暂不支持TUIC v4节点".to_string());
+            }
+            let uuid = clash_str(clash_node, "uuid").ok_or("缺少UUID")?;
+            let mut node = json!({
+                "tag": name,
+                "type": "tuic",
+                "server": server,
+                "server_port": port,
+                "uuid": uuid,
+                "password": clash_str(clash_node, "password").unwrap_or_default()
+            });
+            if let Some(cc) = clash_str(clash_node, "congestion-controller") {
+                node["congestion_control"] = json!(cc);
+            }
+            if let Some(mode) = clash_str(clash_node, "udp-relay-mode") {
+                node["udp_relay_mode"] = json!(mode);
+            }
+            if clash_bool(clash_node, "reduce-rtt") == Some(true) {
+                node["zero_rtt_handshake"] = json!(true);
+            }
+            if let Some(heartbeat) = clash_u64(clash_node, "heartbeat-interval") {
+                node["heartbeat"] = json!(format!("{}ms", heartbeat));
+            }
+            apply_clash_tls(&mut node, clash_node, true, false);
+            if clash_bool(clash_node, "disable-sni") == Some(true) {
+                node["tls"]["disable_sni"] = json!(true);
+            }
+            node
+        },
+        "wireguard" => {
+            let private_key = clash_str(clash_node, "private-key").ok_or("缺少私钥")?;
+            // Clash.Meta 的多peer写法取第一个peer
+            let peer = clash_node
+                .get("peers")
+                .and_then(|p| p.as_array())
+                .and_then(|p| p.first())
+                .unwrap_or(clash_node);
+            let public_key = clash_str(peer, "public-key").ok_or("缺少对端公钥")?;
 
-// 更新所有选择器的outbounds列表
-fn update_selector_outbounds(outbounds_array: &mut Vec<Value>, nodes: &Vec<Value>) {
-    let node_tags: Vec<String> = nodes.iter()
-        .filter_map(|node| node.get("tag").and_then(|t| t.as_str()).map(|s| s.to_string()))
-        .collect();
-    
-    // 处理所有selector类型的出站
-    for outbound in outbounds_array.iter_mut() {
-        if outbound.get("type").and_then(|t| t.as_str()) == Some("selector") {
-            let tag = outbound.get("tag").and_then(|t| t.as_str());
-            if tag == Some("手动切换") || tag == Some("自动选择") {
-                continue; // 已在前面单独处理
+            let mut local_address = Vec::new();
+            if let Some(ip) = clash_str(clash_node, "ip") {
+                local_address.push(if ip.contains('/') { ip } else { format!("{}/32", ip) });
             }
-            
-            if let Some(outbound_tags) = outbound.get_mut("outbounds") {
-                if let Some(array) = outbound_tags.as_array_mut() {
-                    // 保留前两个元素（通常是proxy和自动选择）
-                    if array.len() >= 2 {
-                        let first_two = array[0..2].to_vec();
-                        array.clear();
-                        array.extend(first_two);
-                    }
-                    
-                    // 添加所有节点标签
-                    for tag in &node_tags {
-                        array.push(json!(tag));
-                    }
+            if let Some(ipv6) = clash_str(clash_node, "ipv6") {
+                local_address.push(if ipv6.contains('/') { ipv6 } else { format!("{}/128", ipv6) });
+            }
+            if local_address.is_empty() {
+                return Err("缺少本地地址".to_string());
+            }
+
+            let mut node = json!({
+                "tag": name,
+                "type": "wireguard",
+                "server": clash_str(peer, "server").unwrap_or(server),
+                "server_port": clash_u64(peer, "port").unwrap_or(port),
+                "local_address": local_address,
+                "private_key": private_key,
+                "peer_public_key": public_key
+            });
+            if let Some(psk) = clash_str(peer, "pre-shared-key").filter(|k| !k.is_empty()) {
+                node["pre_shared_key"] = json!(psk);
+            }
+            if let Some(reserved) = peer.get("reserved").filter(|r| !r.is_null()) {
+                node["reserved"] = reserved.clone();
+            }
+            if let Some(mtu) = clash_u64(clash_node, "mtu") {
+                node["mtu"] = json!(mtu);
+            }
+            node
+        },
+        "ssh" => {
+            let mut node = json!({
+                "tag": name,
+                "type": "ssh",
+                "server": server,
+                "server_port": port,
+                "user": clash_str(clash_node, "username").unwrap_or_else(|| "root".to_string())
+            });
+            if let Some(password) = clash_str(clash_node, "password") {
+                node["password"] = json!(password);
+            }
+            if let Some(key) = clash_str(clash_node, "private-key") {
+                // Clash 中的私钥既可以是内容也可以是路径
+                if key.contains("PRIVATE KEY") {
+                    node["private_key"] = json!(key);
+                } else {
+                    node["private_key_path"] = json!(key);
                 }
             }
+            if let Some(passphrase) = clash_str(clash_node, "private-key-passphrase") {
+                node["private_key_passphrase"] = json!(passphrase);
+            }
+            if let Some(host_key) = clash_string_list(clash_node, "host-key") {
+                node["host_key"] = json!(host_key);
+            }
+            if let Some(algorithms) = clash_string_list(clash_node, "host-key-algorithms") {
+                node["host_key_algorithms"] = json!(algorithms);
+            }
+            node
+        },
+        "socks5" => {
+            if clash_bool(clash_node, "tls") == Some(true) {
+                return Err("sing-box的socks出站不支持TLS".to_string());
+            }
+            let mut node = json!({
+                "tag": name,
+                "type": "socks",
+                "server": server,
+                "server_port": port,
+                "version": "5"
+            });
+            apply_clash_auth(&mut node, clash_node);
+            if clash_bool(clash_node, "udp") == Some(false) {
+                node["network"] = json!("tcp");
+            }
+            node
+        },
+        "http" => {
+            let mut node = json!({
+                "tag": name,
+                "type": "http",
+                "server": server,
+                "server_port": port
+            });
+            apply_clash_auth(&mut node, clash_node);
+            if let Some(headers) = clash_node.get("headers").and_then(|h| h.as_object()) {
+                node["headers"] = json!(headers);
+            }
+            apply_clash_tls(&mut node, clash_node, false, false);
+            node
+        },
+        other => return Err(format!("不支持的节点类型: {}", other)),
+    };
+
+    Ok(node)
+}
+
+// 处理TLS、SNI、ALPN、证书校验、uTLS指纹以及Reality设置
+fn apply_clash_tls(node: &mut Value, clash_node: &Value, force_tls: bool, utls_default: bool) {
+    let reality_opts = clash_node.get("reality-opts").filter(|r| r.is_object());
+    let enabled = force_tls || clash_bool(clash_node, "tls") == Some(true) || reality_opts.is_some();
+    if !enabled {
+        return;
+    }
+
+    let mut tls = json!({
+        "enabled": true
+    });
+
+    if let Some(sni) = clash_str(clash_node, "servername").or_else(|| clash_str(clash_node, "sni")) {
+        if !sni.is_empty() {
+            tls["server_name"] = json!(sni);
         }
     }
+
+    if clash_bool(clash_node, "skip-cert-verify") == Some(true) {
+        tls["insecure"] = json!(true);
+    }
+
+    if let Some(alpn) = clash_string_list(clash_node, "alpn") {
+        tls["alpn"] = json!(alpn);
+    }
+
+    // 指纹优先使用节点的 client-fingerprint，基于TCP的协议默认使用chrome
+    let fingerprint = clash_str(clash_node, "client-fingerprint")
+        .filter(|fp| !fp.is_empty())
+        .or_else(|| (utls_default || reality_opts.is_some()).then(|| "chrome".to_string()));
+    if let Some(fingerprint) = fingerprint {
+        tls["utls"] = json!({
+            "enabled": true,
+            "fingerprint": fingerprint
+        });
+    }
+
+    if let Some(reality) = reality_opts {
+        tls["reality"] = json!({
+            "enabled": true,
+            "public_key": clash_str(reality, "public-key").unwrap_or_default(),
+            "short_id": clash_str(reality, "short-id").unwrap_or_default()
+        });
+    }
+
+    node["tls"] = tls;
 }
 
-// 处理订阅内容（手动添加）
-fn process_subscription_content(content: String) -> Result<(), Box<dyn Error>> {
-    // 提取节点信息
-    let extracted_nodes = extract_nodes_from_subscription(&content)?;
-    
-    // 读取模板文件
-    let template_path = get_template_path();
-    let mut template_file = File::open(&template_path)?;
-    let mut template_content = String::new();
-    template_file.read_to_string(&mut template_content)?;
-    
-    // 将模板内容解析为JSON对象
-    let mut config: Value = serde_json::from_str(&template_content)?;
-    
-    // 应用相同的节点合并逻辑
-    if let Some(config_obj) = config.as_object_mut() {
-        if let Some(outbounds) = config_obj.get_mut("outbounds") {
-            if let Some(outbounds_array) = outbounds.as_array_mut() {
-                // 更新自动选择和其他选择器
-                if let Some(auto_select) = outbounds_array.iter_mut().find(|o| {
-                    o.get("tag").and_then(|t| t.as_str()) == Some("自动选择")
-                }) {
-                    if let Some(outbound_tags) = auto_select.get_mut("outbounds") {
-                        let node_tags: Vec<Value> = extracted_nodes.iter()
-                            .map(|node| json!(node.get("tag").unwrap().as_str().unwrap()))
-                            .collect();
-                        *outbound_tags = json!(node_tags);
+// 处理ws、grpc、h2、http和httpupgrade传输方式
+fn apply_clash_transport(node: &mut Value, clash_node: &Value) -> Result<(), String> {
+    let network = match clash_str(clash_node, "network") {
+        Some(network) => network,
+        None => return Ok(()),
+    };
+
+    let transport = match network.as_str() {
+        "tcp" => return Ok(()),
+        "ws" => {
+            let ws_opts = clash_node.get("ws-opts").cloned().unwrap_or(json!({}));
+            // Clash.Meta 通过 v2ray-http-upgrade 标记 httpupgrade 传输
+            let http_upgrade = clash_bool(&ws_opts, "v2ray-http-upgrade") == Some(true);
+            let mut transport = json!({
+                "type": if http_upgrade { "httpupgrade" } else { "ws" }
+            });
+
+            if let Some(path) = clash_str(&ws_opts, "path") {
+                transport["path"] = json!(path);
+            }
+
+            if let Some(headers) = ws_opts.get("headers").and_then(|h| h.as_object()) {
+                if http_upgrade {
+                    if let Some(host) = headers.get("Host").and_then(|h| h.as_str()) {
+                        transport["host"] = json!(host);
                     }
+                } else {
+                    transport["headers"] = json!(headers);
                 }
-                
-                if let Some(proxy_select) = outbounds_array.iter_mut().find(|o| {
-                    o.get("tag").and_then(|t| t.as_str()) == Some("手动切换")
-                }) {
-                    if let Some(outbound_tags) = proxy_select.get_mut("outbounds") {
-                        let mut tags = vec![json!("自动选择")];
-                        for node in &extracted_nodes {
-                            tags.push(json!(node.get("tag").unwrap().as_str().unwrap()));
-                        }
-                        *outbound_tags = json!(tags);
-                    }
+            }
+
+            if !http_upgrade {
+                if let Some(max_early_data) = clash_u64(&ws_opts, "max-early-data") {
+                    transport["max_early_data"] = json!(max_early_data);
                 }
-                
-                // 更新其他选择器
-                update_selector_outbounds(outbounds_array, &extracted_nodes);
-                
-                // 添加节点到outbounds
-                for node in extracted_nodes {
-                    outbounds_array.push(node);
+                if let Some(header_name) = clash_str(&ws_opts, "early-data-header-name") {
+                    transport["early_data_header_name"] = json!(header_name);
                 }
             }
-        }
-    }
-    
-    // 保存配置到文件
-    let work_dir = get_work_dir();
-    let path = Path::new(&work_dir).join("sing-box/config.json");
-    let mut file = File::create(path)?;
-    let config_str = serde_json::to_string_pretty(&config)?;
-    file.write_all(config_str.as_bytes())?;
-
-    info!("订阅内容已处理并应用到模板");
+            transport
+        },
+        "grpc" => {
+            let grpc_opts = clash_node.get("grpc-opts").cloned().unwrap_or(json!({}));
+            json!({
+                "type": "grpc",
+                "service_name": clash_str(&grpc_opts, "grpc-service-name").unwrap_or_default()
+            })
+        },
+        "h2" => {
+            let h2_opts = clash_node.get("h2-opts").cloned().unwrap_or(json!({}));
+            let mut transport = json!({
+                "type": "http"
+            });
+            if let Some(host) = clash_string_list(&h2_opts, "host") {
+                transport["host"] = json!(host);
+            }
+            if let Some(path) = clash_str(&h2_opts, "path") {
+                transport["path"] = json!(path);
+            }
+            transport
+        },
+        "http" => {
+            let http_opts = clash_node.get("http-opts").cloned().unwrap_or(json!({}));
+            let mut transport = json!({
+                "type": "http"
+            });
+            if let Some(method) = clash_str(&http_opts, "method") {
+                transport["method"] = json!(method);
+            }
+            // sing-box 只支持单个路径，取第一个
+            if let Some(path) = clash_string_list(&http_opts, "path").and_then(|p| p.into_iter().next()) {
+                transport["path"] = json!(path);
+            }
+            if let Some(headers) = http_opts.get("headers").and_then(|h| h.as_object()) {
+                let mut headers = headers.clone();
+                if let Some(host) = headers.remove("Host") {
+                    transport["host"] = host;
+                }
+                if !headers.is_empty() {
+                    transport["headers"] = json!(headers);
+                }
+            }
+            transport
+        },
+        other => return Err(format!("不支持的传输方式: {}", other)),
+    };
+
+    node["transport"] = transport;
     Ok(())
 }
 
-// 获取当前代理模式
-#[tauri::command]
-pub fn get_current_proxy_mode() -> Result<String, String> {
-    info!("正在获取当前代理模式");
-    
-    let work_dir = get_work_dir();
-    let path = Path::new(&work_dir).join("sing-box/config.json");
-    
-    // 检查配置文件是否存在
-    if !path.exists() {
-        return Ok("rule".to_string()); // 默认返回rule模式
-    }
-    
-    // 读取配置文件
-    match read_proxy_mode_from_config(&path) {
-        Ok(mode) => {
-            info!("当前代理模式为: {}", mode);
-            Ok(mode)
+// 处理Shadowsocks插件
+fn apply_clash_ss_plugin(node: &mut Value, clash_node: &Value) -> Result<(), String> {
+    let plugin = match clash_str(clash_node, "plugin").filter(|p| !p.is_empty()) {
+        Some(plugin) => plugin,
+        None => return Ok(()),
+    };
+    let opts = clash_node.get("plugin-opts").cloned().unwrap_or(json!({}));
+
+    let plugin_opts = match plugin.as_str() {
+        "obfs" => {
+            let mut plugin_opts = format!("obfs={}", clash_str(&opts, "mode").unwrap_or_else(|| "http".to_string()));
+            if let Some(host) = clash_str(&opts, "host") {
+                plugin_opts.push_str(&format!(";obfs-host={}", host));
+            }
+            node["plugin"] = json!("obfs-local");
+            plugin_opts
         },
-        Err(e) => {
-            error!("获取代理模式失败: {}", e);
-            Ok("rule".to_string()) // 出错时默认返回rule模式
-        }
+        "v2ray-plugin" => {
+            let mut plugin_opts = format!("mode={}", clash_str(&opts, "mode").unwrap_or_else(|| "websocket".to_string()));
+            if clash_bool(&opts, "tls") == Some(true) {
+                plugin_opts.push_str(";tls");
+            }
+            if let Some(host) = clash_str(&opts, "host") {
+                plugin_opts.push_str(&format!(";host={}", host));
+            }
+            if let Some(path) = clash_str(&opts, "path") {
+                plugin_opts.push_str(&format!(";path={}", path));
+            }
+            if clash_bool(&opts, "mux") == Some(true) {
+                plugin_opts.push_str(";mux=1");
+            }
+            node["plugin"] = json!("v2ray-plugin");
+            plugin_opts
+        },
+        other => return Err(format!("不支持的Shadowsocks插件: {}", other)),
+    };
+
+    node["plugin_opts"] = json!(plugin_opts);
+    Ok(())
+}
+
+// 处理用户名和密码认证
+fn apply_clash_auth(node: &mut Value, clash_node: &Value) {
+    if let Some(username) = clash_str(clash_node, "username") {
+        node["username"] = json!(username);
+    }
+    if let Some(password) = clash_str(clash_node, "password") {
+        node["password"] = json!(password);
     }
 }
 
-// 从配置文件中读取代理模式
-fn read_proxy_mode_from_config(config_path: &Path) -> Result<String, Box<dyn Error>> {
-    // 读取配置文件
-    let mut file = File::open(config_path)?;
-    let mut content = String::new();
-    file.read_to_string(&mut content)?;
-    
-    // 解析JSON
-    let json: serde_json::Value = serde_json::from_str(&content)?;
-    
-    // 尝试读取experimental.clash_api.default_mode
-    if let Some(experimental) = json.get("experimental") {
-        if let Some(clash_api) = experimental.get("clash_api") {
-            if let Some(default_mode) = clash_api.get("default_mode") {
-                if let Some(mode) = default_mode.as_str() {
-                    return Ok(mode.to_string());
-                }
-            }
-        }
+// 读取字符串字段，兼容YAML中被解析为数字的值
+fn clash_str(node: &Value, key: &str) -> Option<String> {
+    match node.get(key)? {
+        Value::String(s) => Some(s.clone()),
+        Value::Number(n) => Some(n.to_string()),
+        _ => None,
     }
-    
-    // 如果找不到，返回默认的rule模式
-    Ok("rule".to_string())
-} 
\ No newline at end of file
+}
+
+// 读取数字字段，兼容字符串形式的数字
+fn clash_u64(node: &Value, key: &str) -> Option<u64> {
+    match node.get(key)? {
+        Value::Number(n) => n.as_u64(),
+        Value::String(s) => s.trim().parse().ok(),
+        _ => None,
+    }
+}
+
+// 读取布尔字段，兼容 "true"/"false" 字符串
+fn clash_bool(node: &Value, key: &str) -> Option<bool> {
+    match node.get(key)? {
+        Value::Bool(b) => Some(*b),
+        Value::String(s) => s.trim().parse().ok(),
+        _ => None,
+    }
+}
+
+// 读取字符串列表字段，单个字符串按逗号拆分
+fn clash_string_list(node: &Value, key: &str) -> Option<Vec<String>> {
+    let list: Vec<String> = match node.get(key)? {
+        Value::Array(items) => items
+            .iter()
+            .filter_map(|item| item.as_str().map(|s| s.trim().to_string()))
+            .collect(),
+        Value::String(s) => s.split(',').map(|s| s.trim().to_string()).collect(),
+        _ => return None,
+    };
+    let list: Vec<String> = list.into_iter().filter(|s| !s.is_empty()).collect();
+    if list.is_empty() {
+        None
+    } else {
+        Some(list)
+    }
+}
+
+// 解析带宽字段（如 "100 Mbps"、"1 Gbps"、"500 Kbps" 或 100）为 Mbps，没有单位时按 Mbps 处理
+// 单位无法识别时返回空，不猜测带宽
+fn clash_bandwidth(node: &Value, key: &str) -> Option<u64> {
+    let value = clash_str(node, key)?;
+    let value = value.trim();
+    let split = value
+        .find(|c: char| !c.is_ascii_digit() && c != '.')
+        .unwrap_or(value.len());
+    let (number, unit) = value.split_at(split);
+    let number: f64 = number.parse().ok()?;
+
+    let mbps = match unit.trim().to_ascii_lowercase().as_str() {
+        "" | "m" | "mbps" => number,
+        "k" | "kbps" => number / 1000.0,
+        "g" | "gbps" => number * 1000.0,
+        "t" | "tbps" => number * 1_000_000.0,
+        _ => {
+            warn!("无法识别的带宽单位，已忽略: {} = {}", key, value);
+            return None;
+        }
+    };
+    Some(mbps.ceil() as u64)
+}
+
+// 获取当前代理模式
+#[tauri::command]
+pub fn get_current_proxy_mode() -> Result<String, String> {
+    let mode = settings_util::load_settings().clash_mode;
+    info!("当前代理模式为: {}", mode);
+    Ok(mode)
+}
@@ -1,7 +1,12 @@
-use crate::app::constants::{messages, process};
+use crate::app::constants::messages;
+use crate::utils::download_util;
+#[cfg(windows)]
+use crate::app::constants::process;
+#[cfg(windows)]
 use std::os::windows::process::CommandExt;
 
 // 以管理员权限重启
+#[cfg(windows)]
 #[tauri::command]
 pub fn restart_as_admin() -> Result<(), String> {
     let current_exe =
@@ -21,7 +26,15 @@ pub fn restart_as_admin() -> Result<(), String> {
     }
 }
 
+// 以管理员权限重启（仅支持 Windows）
+#[cfg(not(windows))]
+#[tauri::command]
+pub fn restart_as_admin() -> Result<(), String> {
+    Err(format!("{}: 当前系统不支持", messages::ERR_RESTART_FAILED))
+}
+
 // 检查是否有管理员权限
+#[cfg(windows)]
 #[tauri::command]
 pub fn check_admin() -> bool {
     let result = std::process::Command::new("net")
@@ -33,4 +46,20 @@ pub fn check_admin() -> bool {
         Ok(output) => output.status.success(),
         Err(_) => false,
     }
-} 
\ No newline at end of file
+}
+
+// 检查是否有root权限
+#[cfg(unix)]
+#[tauri::command]
+pub fn check_admin() -> bool {
+    unsafe { libc::geteuid() == 0 }
+}
+
+// 取消正在进行的下载，id 为 kernel 或 update，为空时取消所有下载
+#[tauri::command]
+pub fn cancel_download(id: Option<String>) -> Result<(), String> {
+    match download_util::cancel(id.as_deref()) {
+        0 => Err("没有正在进行的下载".to_string()),
+        _ => Ok(()),
+    }
+}
@@ -1,72 +1,177 @@
 use crate::utils::app_util::get_work_dir;
 use serde_json::json;
+#[cfg(windows)]
 use std::os::windows::process::CommandExt;
 use std::path::Path;
 use tauri::Emitter;
-use crate::app::constants::{api, messages};
+use crate::app::constants::{api, messages, network};
+use crate::entity::github_model::{Asset, Release};
+use crate::entity::settings_model::UpdateChannel;
+use crate::utils::{download_util, settings_util, verify_util};
+use semver::Version;
+use std::time::Duration;
+use tauri::{AppHandle, Manager, Runtime};
+use tracing::{error, info, warn};
 
 // 添加新的结构体用于版本信息
-#[derive(serde::Serialize)]
+#[derive(Debug, Clone, serde::Serialize)]
 pub struct UpdateInfo {
     pub latest_version: String,
     pub download_url: String,
     pub has_update: bool,
+    // 发布说明
+    pub release_notes: Option<String>,
+    pub prerelease: bool,
+    pub published_at: Option<String>,
+    // 用户已选择跳过该版本
+    pub skipped: bool,
 }
 
 // 检查更新
 #[tauri::command]
 pub async fn check_update(current_version: String) -> Result<UpdateInfo, String> {
-    let client = reqwest::Client::new();
+    let settings = settings_util::load_settings();
+    let releases = fetch_releases().await?;
+    let (release, latest) = select_release(&releases, settings.update_channel)
+        .ok_or_else(|| format!("{}: 没有可用的发布版本", messages::ERR_GET_VERSION_FAILED))?;
 
-    // 获取最新版本信息
-    let response = client
-        .get(api::GITHUB_API_URL)
-        .header("User-Agent", api::USER_AGENT)
-        .send()
-        .await
-        .map_err(|e| format!("{}: {}", messages::ERR_GET_VERSION_FAILED, e))?;
+    let has_update = is_newer(&latest, &current_version);
 
-    let release: serde_json::Value = response
-        .json()
-        .await
-        .map_err(|e| format!("{}: {}", messages::ERR_GET_VERSION_FAILED, e))?;
+    // 获取当前系统的安装程序
+    let download_url = select_asset(&release.assets)
+        .map(|asset| asset.browser_download_url.clone())
+        .unwrap_or_default();
+    if has_update && download_url.is_empty() {
+        return Err(format!("{}: 没有适用于当前系统的安装程序", messages::ERR_GET_VERSION_FAILED));
+    }
 
-    // 获取最新版本号
-    let tag_name = release["tag_name"]
-        .as_str()
-        .ok_or_else(|| format!("{}: 无法解析版本号", messages::ERR_GET_VERSION_FAILED))
-        .map(|v| v.trim_start_matches('v').to_string())?;
-    
+    let latest_version = latest.to_string();
+    Ok(UpdateInfo {
+        skipped: settings.skipped_version.as_deref() == Some(latest_version.as_str()),
+        latest_version,
+        download_url,
+        has_update,
+        release_notes: release.body.clone(),
+        prerelease: release.prerelease || !latest.pre.is_empty(),
+        published_at: release.published_at.clone(),
+    })
+}
+
+// 跳过指定版本，该版本不再自动提示；为空时取消跳过
+#[tauri::command]
+pub fn skip_update_version(version: Option<String>) -> Result<(), String> {
+    // 统一保存为不带 v 前缀的版本号，与检查更新时的比较方式一致
+    let version = version
+        .map(|version| {
+            parse_version(&version)
+                .map(|parsed| parsed.to_string())
+                .ok_or_else(|| format!("无效的版本号: {}", version))
+        })
+        .transpose()?;
+    settings_util::update_settings(|settings| settings.skipped_version = version)?;
+    Ok(())
+}
 
-    // 获取下载链接
-    let assets = release["assets"].as_array().ok_or_else(|| {
-        format!("{}: 无法获取下载资源", messages::ERR_GET_VERSION_FAILED)
-    })?;
-
-    // 查找Windows安装程序
-    let mut download_url = String::new();
-    for asset in assets {
-        let name = asset["name"].as_str().unwrap_or("");
-        if name.ends_with(".msi") || name.ends_with(".exe") {
-            download_url = asset["browser_download_url"]
-                .as_str()
-                .unwrap_or("")
-                .to_string();
-            break;
+// 定期检查更新，发现未跳过的新版本时发送 update-available 事件，每个版本只提示一次
+pub fn start_update_checker<R: Runtime>(app: AppHandle<R>) {
+    let current_version = app.package_info().version.to_string();
+    tauri::async_runtime::spawn(async move {
+        tokio::time::sleep(Duration::from_secs(api::UPDATE_CHECK_DELAY)).await;
+        let mut ticker = tokio::time::interval(Duration::from_secs(api::UPDATE_CHECK_INTERVAL));
+        let mut notified: Option<String> = None;
+
+        loop {
+            ticker.tick().await;
+            if !settings_util::load_settings().auto_check_update {
+                continue;
+            }
+
+            match check_update(current_version.clone()).await {
+                Ok(info) if info.has_update && !info.skipped => {
+                    if notified.as_deref() != Some(info.latest_version.as_str()) {
+                        info!("发现新版本: {}", info.latest_version);
+                        notified = Some(info.latest_version.clone());
+                        let _ = app.emit("update-available", info);
+                    }
+                }
+                Ok(_) => {}
+                Err(e) => warn!("检查更新失败: {}", e),
+            }
         }
+    });
+}
+
+// 解析版本号，允许 v 前缀
+fn parse_version(version: &str) -> Option<Version> {
+    Version::parse(version.trim().trim_start_matches('v')).ok()
+}
+
+// 最新版本是否比当前版本新，按语义化版本比较，较旧的版本不算更新
+fn is_newer(latest: &Version, current_version: &str) -> bool {
+    match parse_version(current_version) {
+        Some(current) => *latest > current,
+        None => {
+            warn!("无法解析当前版本号: {}", current_version);
+            latest.to_string() != current_version.trim_start_matches('v')
+        }
+    }
+}
+
+// 选择通道内版本号最高的发布，稳定通道不包含预发布版本
+fn select_release(releases: &[Release], channel: UpdateChannel) -> Option<(&Release, Version)> {
+    releases
+        .iter()
+        .filter(|release| !release.draft)
+        .filter_map(|release| parse_version(&release.tag_name).map(|version| (release, version)))
+        .filter(|(release, version)| {
+            channel == UpdateChannel::Prerelease || (!release.prerelease && version.pre.is_empty())
+        })
+        .max_by(|(_, a), (_, b)| a.cmp(b))
+}
+
+// 当前系统的安装程序扩展名，按优先顺序排列
+fn installer_extensions() -> &'static [&'static str] {
+    if cfg!(windows) {
+        &[".exe", ".msi"]
+    } else if cfg!(target_os = "macos") {
+        &[".dmg"]
+    } else {
+        &[".appimage", ".deb"]
     }
+}
 
-    if download_url.is_empty() {
-        return Err(format!("{}: 无法获取下载链接", messages::ERR_GET_VERSION_FAILED));
+// 当前架构在文件名中的写法
+fn arch_names() -> &'static [&'static str] {
+    match std::env::consts::ARCH {
+        "x86_64" => &["x64", "amd64"],
+        "aarch64" => &["arm64"],
+        "x86" => &["x86", "i686", "386"],
+        _ => &[],
     }
+}
 
-    // 简单比较版本号
-    let has_update = tag_name != current_version;
+const KNOWN_ARCH_NAMES: &[&str] = &["x64", "amd64", "arm64", "x86", "i686", "386"];
 
-    Ok(UpdateInfo {
-        latest_version: tag_name.to_string(),
-        download_url,
-        has_update,
+// 文件名中的架构，例如 sing-box-windows_1.7.5_x64-setup.exe 中的 x64
+fn asset_arch(name: &str) -> Option<String> {
+    let name = name.to_lowercase().replace("x86_64", "x64").replace("aarch64", "arm64");
+    name.split(['-', '_', '.', ' '])
+        .find(|part| KNOWN_ARCH_NAMES.contains(part))
+        .map(|part| part.to_string())
+}
+
+// 选择当前系统和架构的安装程序，文件名中没有架构时视为通用
+fn select_asset(assets: &[Asset]) -> Option<&Asset> {
+    installer_extensions().iter().find_map(|extension| {
+        let candidates: Vec<&Asset> = assets
+            .iter()
+            .filter(|asset| asset.name.to_lowercase().ends_with(extension))
+            .collect();
+        candidates
+            .iter()
+            .find(|asset| asset_arch(&asset.name).is_some_and(|arch| arch_names().contains(&arch.as_str())))
+            .or_else(|| candidates.iter().find(|asset| asset_arch(&asset.name).is_none()))
+            .copied()
     })
 }
 
@@ -77,8 +182,27 @@ pub async fn download_and_install_update(
     download_url: String,
 ) -> Result<(), String> {
     
+    // 找到下载地址对应的发布资源，用于获取校验值和签名
+    let releases = fetch_releases().await?;
+    let (release, asset) = releases
+        .iter()
+        .find_map(|release| {
+            release
+                .assets
+                .iter()
+                .find(|asset| asset.browser_download_url == download_url)
+                .map(|asset| (release, asset))
+        })
+        .ok_or("下载地址不属于已发布的版本")?;
+
+    // 公钥或签名文件不可用时无法校验，不开始下载
+    verify_util::decode_public_key(api::UPDATE_PUBLIC_KEY)?;
+    find_signature_asset(release, asset)?;
+
+    // 保留安装程序的扩展名，.msi 需要通过 msiexec 安装
+    let extension = if asset.name.to_lowercase().ends_with(".msi") { "msi" } else { "exe" };
     let work_dir = get_work_dir();
-    let download_path = Path::new(&work_dir).join("update.exe");
+    let download_path = Path::new(&work_dir).join(format!("update.{}", extension));
 
     // 发送开始下载事件
     let _ = window.emit(
@@ -92,39 +216,214 @@ pub async fn download_and_install_update(
 
     // 下载更新文件
     let window_clone = window.clone();
-    // 使用fallback下载函数
-    if let Err(e) = crate::utils::file_util::download_with_fallback(
+    if let Err(e) = download_util::download(
+        network::UPDATE_DOWNLOAD_ID,
         &download_url,
-        download_path.to_str().unwrap(),
+        &download_path,
         move |progress| {
             let _ = window_clone.emit(
                 "update-progress",
                 json!({
                     "status": "downloading",
-                    "progress": progress,
-                    "message": format!("正在下载: {}%", progress)
+                    "progress": progress.percent,
+                    "downloaded": progress.downloaded,
+                    "total": progress.total,
+                    "speed": progress.speed,
+                    "message": format!("正在下载: {}%", progress.percent)
                 }),
             );
         },
     ).await {
+        if e == messages::ERR_DOWNLOAD_CANCELLED {
+            let _ = window.emit(
+                "update-progress",
+                json!({
+                    "status": "cancelled",
+                    "progress": 0,
+                    "message": e
+                }),
+            );
+            return Err(e);
+        }
         return Err(format!("下载更新失败: {}", e));
     }
 
+    // 校验通过后才启动安装程序
+    let _ = window.emit(
+        "update-progress",
+        json!({
+            "status": "verifying",
+            "progress": 100,
+            "message": "正在校验更新文件..."
+        }),
+    );
+    let sha256 = match verify_update(release, asset, &download_path).await {
+        Ok(sha256) => sha256,
+        Err(e) => {
+            error!("更新文件校验失败: {}", e);
+            let _ = std::fs::remove_file(&download_path);
+            return Err(e);
+        }
+    };
+    info!("更新文件校验通过: {} (SHA-256: {})", asset.name, sha256);
+
     // 发送下载完成事件
     let _ = window.emit(
         "update-progress",
         json!({
             "status": "completed",
             "progress": 100,
+            "sha256": sha256,
             "message": "下载完成，准备安装..."
         }),
     );
 
     // 启动安装程序
-    std::process::Command::new(download_path)
-        .creation_flags(0x08000000)
+    let mut command = if extension == "msi" {
+        let mut command = std::process::Command::new("msiexec");
+        command.arg("/i").arg(&download_path);
+        command
+    } else {
+        std::process::Command::new(&download_path)
+    };
+    #[cfg(windows)]
+    command.creation_flags(0x08000000);
+    command
         .spawn()
         .map_err(|e| format!("启动安装程序失败: {}", e))?;
 
     Ok(())
-} 
\ No newline at end of file
+} 
+
+// 获取最近的发布版本，包括预发布版本
+async fn fetch_releases() -> Result<Vec<Release>, String> {
+    let response = reqwest::Client::new()
+        .get(format!("{}?per_page=20", api::GITHUB_RELEASES_URL))
+        .header("User-Agent", api::USER_AGENT)
+        .send()
+        .await
+        .map_err(|e| format!("{}: {}", messages::ERR_GET_VERSION_FAILED, e))?;
+
+    if !response.status().is_success() {
+        return Err(format!("{}: {}", messages::ERR_GET_VERSION_FAILED, response.status()));
+    }
+
+    response
+        .json()
+        .await
+        .map_err(|e| format!("{}: {}", messages::ERR_GET_VERSION_FAILED, e))
+}
+
+// 校验更新文件的 SHA-256 和签名，返回文件的 SHA-256
+// 安装程序会被直接运行，签名缺失或校验失败时都拒绝安装
+async fn verify_update(release: &Release, asset: &Asset, path: &Path) -> Result<String, String> {
+    let expected = verify_util::expected_sha256(&release.assets, asset).await?;
+    let sha256 = verify_util::verify_sha256(path, expected.as_deref())?;
+
+    let signature_asset = find_signature_asset(release, asset)?;
+    let signature = verify_util::fetch_signature(&signature_asset.browser_download_url).await?;
+    verify_util::verify_signature(path, &signature, api::UPDATE_PUBLIC_KEY)?;
+
+    Ok(sha256)
+}
+
+// 查找安装程序对应的 <文件名>.sig 签名文件
+fn find_signature_asset<'a>(release: &'a Release, asset: &Asset) -> Result<&'a Asset, String> {
+    let signature_name = format!("{}.sig", asset.name);
+    release
+        .assets
+        .iter()
+        .find(|a| a.name == signature_name)
+        .ok_or_else(|| format!("发布中缺少更新签名文件: {}，请前往发布页面手动下载更新", signature_name))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn asset(name: &str) -> Asset {
+        serde_json::from_value(json!({
+            "url": "", "id": 0, "node_id": "", "name": name, "label": null, "content_type": "",
+            "state": "uploaded", "size": 0, "download_count": 0, "created_at": "", "updated_at": "",
+            "browser_download_url": format!("https://example.com/{}", name), "digest": null
+        }))
+        .unwrap()
+    }
+
+    fn release(tag: &str, prerelease: bool, draft: bool) -> Release {
+        serde_json::from_value(json!({
+            "url": "", "assets_url": "", "upload_url": "", "html_url": "", "id": 0, "tag_name": tag,
+            "target_commitish": "", "name": tag, "draft": draft, "prerelease": prerelease, "created_at": "",
+            "published_at": null, "assets": [], "tarball_url": null, "zipball_url": null, "body": null
+        }))
+        .unwrap()
+    }
+
+    fn selected(releases: &[Release], channel: UpdateChannel) -> Option<String> {
+        select_release(releases, channel).map(|(_, version)| version.to_string())
+    }
+
+    #[test]
+    fn selects_release_by_channel() {
+        let releases = vec![
+            release("v1.2.0", false, false),
+            release("v1.3.0-beta.1", true, false),
+            // 未标记为预发布但版本号带有预发布后缀
+            release("v1.3.0-rc.1", false, false),
+            release("not-a-version", false, false),
+        ];
+
+        assert_eq!(selected(&releases, UpdateChannel::Stable).as_deref(), Some("1.2.0"));
+        assert_eq!(selected(&releases, UpdateChannel::Prerelease).as_deref(), Some("1.3.0-rc.1"));
+    }
+
+    #[test]
+    fn skips_drafts() {
+        let releases = vec![release("v1.2.0", false, false), release("v2.0.0", false, true)];
+        assert_eq!(selected(&releases, UpdateChannel::Stable).as_deref(), Some("1.2.0"));
+        assert_eq!(selected(&[release("v2.0.0", false, true)], UpdateChannel::Stable), None);
+    }
+
+    #[test]
+    fn compares_versions_semantically() {
+        assert_eq!(parse_version("v1.2.3"), parse_version("1.2.3"));
+        assert!(parse_version("v1.2.3").is_some());
+
+        let latest = Version::parse("1.10.0").unwrap();
+        assert!(is_newer(&latest, "1.9.5"));
+        assert!(is_newer(&latest, "v1.9.5"));
+        assert!(!is_newer(&latest, "1.10.0"));
+        assert!(!is_newer(&latest, "v1.11.0"));
+        assert!(is_newer(&latest, "1.10.0-beta.2"));
+    }
+
+    #[test]
+    fn detects_asset_arch() {
+        assert_eq!(asset_arch("sing-box-windows_1.7.5_x64-setup.exe").as_deref(), Some("x64"));
+        assert_eq!(asset_arch("sing-box-windows_1.7.5_x86_64_en-US.msi").as_deref(), Some("x64"));
+        assert_eq!(asset_arch("sing-box-windows_1.7.5_aarch64.dmg").as_deref(), Some("arm64"));
+        assert_eq!(asset_arch("sing-box-windows_1.7.5-setup.exe"), None);
+    }
+
+    #[test]
+    fn prefers_installer_for_current_arch() {
+        let extension = installer_extensions()[0];
+        let arch = match arch_names().first() {
+            Some(arch) => *arch,
+            None => return,
+        };
+        let other = KNOWN_ARCH_NAMES.iter().find(|name| !arch_names().contains(name)).unwrap();
+
+        let generic = format!("app_1.0.0-setup{}", extension);
+        let native = format!("app_1.0.0_{}-setup{}", arch, extension);
+        let foreign = format!("app_1.0.0_{}-setup{}", other, extension);
+
+        let assets = vec![asset(&foreign), asset(&generic), asset(&native), asset("app_1.0.0.zip")];
+        assert_eq!(select_asset(&assets).unwrap().name, native);
+
+        let assets = vec![asset(&foreign), asset(&generic)];
+        assert_eq!(select_asset(&assets).unwrap().name, generic);
+
+        assert!(select_asset(&[asset(&foreign), asset("app_1.0.0.zip")]).is_none());
+    }
+}
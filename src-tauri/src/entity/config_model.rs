@@ -1,47 +1,181 @@
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
 
-#[derive(Debug, serde::Deserialize, Serialize)]
-#[allow(dead_code)]
+// sing-box 配置文件
+// 每一层都保留未建模的字段，读取后再写回不会丢失用户自定义的内容
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct SingBoxConfig {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub log: Option<LogConfig>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dns: Option<DnsConfig>,
+    #[serde(default)]
+    pub inbounds: Vec<Inbound>,
+    #[serde(default)]
+    pub outbounds: Vec<Outbound>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub route: Option<RouteConfig>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub experimental: Option<ExperimentalConfig>,
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+impl SingBoxConfig {
+    // 按类型查找第一个入站
+    pub fn inbound_by_type_mut(&mut self, inbound_type: &str) -> Option<&mut Inbound> {
+        self.inbounds.iter_mut().find(|i| i.r#type == inbound_type)
+    }
+
+    // 按标签查找出站
+    pub fn outbound_mut(&mut self, tag: &str) -> Option<&mut Outbound> {
+        self.outbounds.iter_mut().find(|o| o.tag == tag)
+    }
+
+    // 获取 Clash API 配置，不存在时创建默认配置
+    pub fn clash_api_mut(&mut self) -> &mut ClashApiConfig {
+        self.experimental
+            .get_or_insert_with(ExperimentalConfig::default)
+            .clash_api
+            .get_or_insert_with(ClashApiConfig::default)
+    }
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct LogConfig {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub disabled: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub level: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub output: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timestamp: Option<bool>,
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct DnsConfig {
+    #[serde(default)]
+    pub servers: Vec<DnsServer>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub rules: Vec<Value>,
+    #[serde(rename = "final", default, skip_serializing_if = "Option::is_none")]
+    pub final_server: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub strategy: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub independent_cache: Option<bool>,
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct DnsServer {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tag: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub address: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub address_resolver: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub strategy: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub detour: Option<String>,
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
 pub struct Inbound {
     pub r#type: String,
-    pub tag: String,
-    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tag: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub listen: Option<String>,
-    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub listen_port: Option<u16>,
-    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub address: Option<Vec<String>>,
-    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub auto_route: Option<bool>,
-    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub strict_route: Option<bool>,
-    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub stack: Option<String>,
-    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub sniff: Option<bool>,
-    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub set_system_proxy: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub users: Option<Vec<Value>>,
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct Outbound {
+    pub r#type: String,
+    #[serde(default)]
+    pub tag: String,
+    // selector 和 urltest 的成员
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub outbounds: Option<Vec<String>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub default: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub detour: Option<String>,
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
 }
 
-#[derive(Debug, serde::Deserialize, Serialize)]
-#[allow(dead_code)]
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct RouteConfig {
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub rules: Vec<Value>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub rule_set: Vec<Value>,
+    #[serde(rename = "final", default, skip_serializing_if = "Option::is_none")]
+    pub final_outbound: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub auto_detect_interface: Option<bool>,
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct ExperimentalConfig {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub clash_api: Option<ClashApiConfig>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cache_file: Option<CacheFileConfig>,
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
 pub struct ClashApiConfig {
-    pub external_controller: String,
-    pub external_ui: String,
-    pub external_ui_download_url: String,
-    pub external_ui_download_detour: String,
-    pub default_mode: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub external_controller: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub external_ui: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub external_ui_download_url: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub external_ui_download_detour: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub secret: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub default_mode: Option<String>,
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
 }
 
-#[derive(Debug, serde::Deserialize, Serialize)]
-#[allow(dead_code)]
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
 pub struct CacheFileConfig {
+    #[serde(default)]
     pub enabled: bool,
-}
-
-#[derive(Debug, serde::Deserialize, Serialize)]
-#[allow(dead_code)]
-pub struct Config {
-    pub clash_api: ClashApiConfig,
-    pub cache_file: CacheFileConfig,
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
 }
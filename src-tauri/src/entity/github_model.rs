@@ -34,4 +34,6 @@ pub struct Asset {
     pub created_at: String,
     pub updated_at: String,
     pub browser_download_url: String,
+    // GitHub 计算的资源摘要，例如 "sha256:..."
+    pub digest: Option<String>,
 }
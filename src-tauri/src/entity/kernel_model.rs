@@ -0,0 +1,48 @@
+use serde::{Deserialize, Serialize};
+
+// 内核版本状态，记录当前使用和切换前使用的版本
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct KernelVersionState {
+    pub active: Option<String>,
+    // 用于回滚
+    pub previous: Option<String>,
+}
+
+// 已安装的内核版本
+#[derive(Debug, Clone, Serialize)]
+pub struct InstalledKernel {
+    pub version: String,
+    pub path: String,
+    pub active: bool,
+    pub previous: bool,
+    // 内核 version 命令的输出，无法运行时为空
+    pub version_info: Option<String>,
+    // 安装时间(Unix时间戳，秒)
+    pub installed_at: u64,
+    // 下载文件的 SHA-256，手动放置的内核为空
+    pub sha256: Option<String>,
+    // 是否与发布的校验值比对通过
+    pub verified: bool,
+}
+
+// 内核安装记录，保存在版本目录中
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct KernelInstallRecord {
+    pub asset: String,
+    pub sha256: String,
+    pub verified: bool,
+}
+
+// GitHub 上发布的内核版本
+#[derive(Debug, Clone, Serialize)]
+pub struct KernelRelease {
+    pub tag: String,
+    pub version: String,
+    pub name: Option<String>,
+    pub prerelease: bool,
+    pub published_at: Option<String>,
+    pub installed: bool,
+    // 当前系统和架构可用的变体，空字符串表示标准版本
+    pub variants: Vec<String>,
+}
@@ -1,2 +1,6 @@
 pub mod config_model;
 pub mod github_model;
+pub mod subscription_model;
+pub mod settings_model;
+pub mod snapshot_model;
+pub mod kernel_model;
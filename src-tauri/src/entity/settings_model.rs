@@ -0,0 +1,79 @@
+use crate::app::constants::network;
+use serde::{Deserialize, Serialize};
+
+// 用户设置，生成配置文件时使用
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct UserSettings {
+    // 混合代理端口
+    pub proxy_port: u16,
+    // 是否启用TUN模式
+    pub tun_enabled: bool,
+    // DNS是否优先使用IPv6
+    pub prefer_ipv6: bool,
+    // Clash 模式(rule、global等)
+    pub clash_mode: String,
+    pub dns: DnsSettings,
+    // 是否允许局域网访问代理端口
+    pub allow_lan: bool,
+    // Clash API 端口
+    pub clash_api_port: u16,
+    // 启动时端口被占用则自动选择空闲端口
+    pub auto_select_port: bool,
+    // 下载的内核变体，例如 legacy-windows-7；为空时自动选择，空字符串表示标准版本
+    pub kernel_variant: Option<String>,
+    // 下载镜像，为地址前缀，空字符串表示直接下载
+    pub download_mirrors: Vec<String>,
+    // 为 true 时同时测试所有镜像，优先使用响应最快的镜像
+    pub race_mirrors: bool,
+    // 应用更新通道
+    pub update_channel: UpdateChannel,
+    // 跳过的版本，该版本不再自动提示更新
+    pub skipped_version: Option<String>,
+    // 是否定期检查更新
+    pub auto_check_update: bool,
+}
+
+// 应用更新通道
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum UpdateChannel {
+    // 只接收正式版本
+    #[default]
+    Stable,
+    // 同时接收预发布版本
+    Prerelease,
+}
+
+impl Default for UserSettings {
+    fn default() -> Self {
+        Self {
+            proxy_port: network::DEFAULT_PROXY_PORT,
+            tun_enabled: false,
+            prefer_ipv6: false,
+            clash_mode: "rule".to_string(),
+            dns: DnsSettings::default(),
+            allow_lan: false,
+            clash_api_port: network::DEFAULT_CLASH_API_PORT,
+            auto_select_port: false,
+            kernel_variant: None,
+            download_mirrors: network::DEFAULT_DOWNLOAD_MIRRORS.iter().map(|m| m.to_string()).collect(),
+            race_mirrors: false,
+            update_channel: UpdateChannel::Stable,
+            skipped_version: None,
+            auto_check_update: true,
+        }
+    }
+}
+
+// 自定义DNS服务器地址，为空时使用模板中的地址
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DnsSettings {
+    // 通过代理访问的DNS
+    pub proxy: Option<String>,
+    // 直连DNS
+    pub direct: Option<String>,
+    // 用于解析DNS服务器域名的DNS
+    pub resolver: Option<String>,
+}
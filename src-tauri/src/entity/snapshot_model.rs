@@ -0,0 +1,31 @@
+use serde::Serialize;
+use serde_json::Value;
+
+// 配置快照信息
+#[derive(Debug, Clone, Serialize)]
+pub struct ConfigSnapshot {
+    pub id: String,
+    // 创建时间(Unix时间戳，秒)
+    pub created_at: u64,
+    pub size: u64,
+}
+
+// 配置差异的类型
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DiffKind {
+    Added,
+    Removed,
+    Changed,
+}
+
+// 单条配置差异，path 为 JSON Pointer 格式
+#[derive(Debug, Clone, Serialize)]
+pub struct ConfigDiff {
+    pub path: String,
+    pub kind: DiffKind,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub old: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub new: Option<Value>,
+}
@@ -0,0 +1,132 @@
+use serde::{Deserialize, Serialize};
+
+// 单个订阅的信息
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Subscription {
+    pub id: String,
+    pub name: String,
+    // 手动添加的订阅没有URL
+    pub url: Option<String>,
+    pub enabled: bool,
+    // 最后更新时间(Unix时间戳，秒)
+    pub last_updated: Option<u64>,
+    pub node_count: usize,
+    // 自动更新间隔(分钟)，为空时不自动更新
+    #[serde(default)]
+    pub update_interval: Option<u64>,
+    // 最近一次更新失败的原因，更新成功后清空
+    #[serde(default)]
+    pub last_error: Option<String>,
+    // 订阅响应头 subscription-userinfo 中的流量和到期信息
+    #[serde(default)]
+    pub user_info: Option<SubscriptionUserInfo>,
+    // 订阅响应头 profile-update-interval 建议的更新间隔(小时)
+    #[serde(default)]
+    pub profile_update_interval: Option<u64>,
+    // 节点过滤、重命名和去重规则
+    #[serde(default)]
+    pub rules: NodeRules,
+}
+
+// 订阅的流量和到期信息，单位为字节，到期时间为Unix时间戳(秒)
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SubscriptionUserInfo {
+    pub upload: u64,
+    pub download: u64,
+    // 为0时表示不限流量
+    pub total: u64,
+    pub expire: Option<u64>,
+}
+
+impl SubscriptionUserInfo {
+    // 已用流量
+    pub fn used(&self) -> u64 {
+        self.upload.saturating_add(self.download)
+    }
+
+    // 剩余流量，不限流量时为空
+    pub fn remaining(&self) -> Option<u64> {
+        if self.total == 0 {
+            None
+        } else {
+            Some(self.total.saturating_sub(self.used()))
+        }
+    }
+}
+
+// 返回给前端的订阅用量信息
+#[derive(Debug, Clone, Serialize)]
+pub struct SubscriptionUsage {
+    pub id: String,
+    pub name: String,
+    pub upload: u64,
+    pub download: u64,
+    pub used: u64,
+    pub total: u64,
+    pub remaining: Option<u64>,
+    pub expire: Option<u64>,
+    // 距离到期的秒数，已过期时为负数
+    pub expires_in: Option<i64>,
+    pub profile_update_interval: Option<u64>,
+    // 流量即将用尽
+    pub quota_warning: bool,
+    // 订阅即将到期或已到期
+    pub expire_warning: bool,
+}
+
+// 订阅注册表，保存在工作目录中
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SubscriptionRegistry {
+    #[serde(default)]
+    pub subscriptions: Vec<Subscription>,
+}
+
+impl SubscriptionRegistry {
+    pub fn find(&self, id: &str) -> Option<&Subscription> {
+        self.subscriptions.iter().find(|s| s.id == id)
+    }
+
+    pub fn find_mut(&mut self, id: &str) -> Option<&mut Subscription> {
+        self.subscriptions.iter_mut().find(|s| s.id == id)
+    }
+}
+
+// 节点规则匹配的字段
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum NodeField {
+    Tag,
+    Type,
+    Server,
+}
+
+// 节点过滤条件，使用正则表达式匹配指定字段
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NodeFilter {
+    pub field: NodeField,
+    pub pattern: String,
+}
+
+// 节点重命名规则，使用正则表达式替换节点名称
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RenameRule {
+    pub pattern: String,
+    #[serde(default)]
+    pub replacement: String,
+}
+
+// 订阅节点的处理规则，依次执行过滤、重命名和去重
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct NodeRules {
+    // 非空时只保留匹配任一条件的节点
+    #[serde(default)]
+    pub include: Vec<NodeFilter>,
+    // 移除匹配任一条件的节点
+    #[serde(default)]
+    pub exclude: Vec<NodeFilter>,
+    #[serde(default)]
+    pub renames: Vec<RenameRule>,
+    // 按服务器、端口和认证信息去重
+    #[serde(default)]
+    pub dedup: bool,
+}
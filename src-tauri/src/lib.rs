@@ -1,17 +1,29 @@
+use crate::app::config_service::{
+    diff_config_snapshot, get_user_settings, list_config_snapshots, restore_config_snapshot,
+    update_user_settings,
+};
 use crate::app::kernel_service::{
-    check_kernel_version, download_latest_kernel, get_process_status,
-    restart_kernel, start_kernel, start_websocket_relay, stop_kernel,
+    check_kernel_version, download_latest_kernel, get_kernel_crashes, get_kernel_logs,
+    get_kernel_metrics, get_process_status, install_kernel_version, list_kernel_releases,
+    list_kernel_versions, remove_kernel_version, restart_kernel, rollback_kernel_version,
+    start_kernel, start_kernel_event_bridge, start_websocket_relay, stop_kernel,
+    switch_kernel_version, PROCESS_MANAGER,
 };
 use crate::app::proxy_service::{
     change_proxy, get_proxies, get_rules, get_version_info, set_system_proxy,
     set_tun_proxy, test_group_delay, toggle_ip_version,
 };
 use crate::app::subscription_service::{
-    add_manual_subscription, download_subscription, get_current_config, get_current_proxy_mode,
-    toggle_proxy_mode,
+    add_manual_subscription, add_subscription, download_subscription, get_current_config,
+    get_current_proxy_mode, get_subscription_usage, list_subscriptions,
+    preview_subscription_rules, refresh_subscription, remove_subscription,
+    set_subscription_enabled, set_subscription_interval, set_subscription_rules,
+    start_subscription_scheduler, toggle_proxy_mode,
+};
+use crate::app::system_service::{cancel_download, check_admin, restart_as_admin};
+use crate::app::update_service::{
+    check_update, download_and_install_update, skip_update_version, start_update_checker,
 };
-use crate::app::system_service::{check_admin, restart_as_admin};
-use crate::app::update_service::{check_update, download_and_install_update};
 use tauri::{AppHandle, Manager};
 use tauri_plugin_autostart::MacosLauncher;
 
@@ -49,6 +61,17 @@ pub fn run() {
                     window.hide().unwrap();
                 }
             }
+
+            // 启动订阅自动更新
+            start_subscription_scheduler(app.handle().clone());
+            // 推送内核状态变化和崩溃事件
+            start_kernel_event_bridge(app.handle().clone());
+            // 定期检查应用更新
+            start_update_checker(app.handle().clone());
+            // 接管应用上次异常退出时遗留的内核
+            tauri::async_runtime::spawn(async {
+                PROCESS_MANAGER.adopt_orphan().await;
+            });
             Ok(())
         })
         .invoke_handler(tauri::generate_handler![
@@ -66,7 +89,18 @@ pub fn run() {
             toggle_ip_version,
             check_update,
             download_and_install_update,
+            skip_update_version,
+            cancel_download,
             get_process_status,
+            get_kernel_logs,
+            get_kernel_crashes,
+            get_kernel_metrics,
+            list_kernel_versions,
+            list_kernel_releases,
+            install_kernel_version,
+            switch_kernel_version,
+            rollback_kernel_version,
+            remove_kernel_version,
             check_kernel_version,
             toggle_proxy_mode,
             get_current_proxy_mode,
@@ -76,6 +110,20 @@ pub fn run() {
             get_version_info,
             get_rules,
             start_websocket_relay,
+            list_subscriptions,
+            add_subscription,
+            remove_subscription,
+            set_subscription_enabled,
+            refresh_subscription,
+            set_subscription_interval,
+            get_subscription_usage,
+            set_subscription_rules,
+            preview_subscription_rules,
+            get_user_settings,
+            update_user_settings,
+            list_config_snapshots,
+            diff_config_snapshot,
+            restore_config_snapshot,
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
@@ -1,21 +1,76 @@
-use super::{ProcessError, ProcessInfo, ProcessStatus, Result};
+use super::metrics::MetricsRecorder;
+use super::output::KernelOutput;
+use super::pid_file::{self, KernelPidFile};
+use super::ports::{self, PortConflict, PortUsage};
+use super::runner::{self, KernelEndpoints, KernelRunner};
+use super::{
+    CrashRecord, KernelMetrics, ProcessConfig, ProcessError, ProcessInfo, ProcessStatus, Result, StatusEvent,
+};
+use crate::app::config_service;
 use crate::utils::app_util::get_work_dir;
-use std::os::windows::process::CommandExt;
-use std::sync::Arc;
-use tokio::process::Command;
-use tokio::sync::RwLock;
+use crate::utils::{config_util, settings_util};
+use std::collections::VecDeque;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU32, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+use tokio::io::{AsyncBufReadExt, AsyncRead, BufReader};
+use tokio::sync::{broadcast, watch, RwLock};
+use tokio::task::JoinHandle;
 use tokio::time::{sleep, Duration};
 use tracing::{error, info, warn};
 use crate::utils::proxy_util::disable_system_proxy;
-use crate::app::constants::{paths, process, messages};
+use crate::app::constants::{network, paths, process, messages};
 
+// 克隆得到的管理器与原管理器共享同一份状态，用于在后台任务中监控进程
+#[derive(Clone)]
 pub struct ProcessManager {
     process_info: Arc<RwLock<ProcessInfo>>,
     child_process: Arc<RwLock<Option<tokio::process::Child>>>,
+    runner: Arc<dyn KernelRunner>,
+    config: ProcessConfig,
+    // 内核输出，启动失败或异常退出时附加到错误信息中
+    output: Arc<Mutex<KernelOutput>>,
+    // 每次手动启动或停止时递增，后台监控任务据此判断进程退出是否为意外
+    generation: Arc<AtomicU64>,
+    // 连续自动重启的次数
+    restart_attempts: Arc<AtomicU32>,
+    crashes: Arc<Mutex<VecDeque<CrashRecord>>>,
+    crash_events: broadcast::Sender<CrashRecord>,
+    status_events: broadcast::Sender<StatusEvent>,
+    // 当前子进程的退出通知，由监控任务在进程退出后发送
+    exit_watch: Arc<Mutex<Option<watch::Receiver<bool>>>>,
+    // 后台监控任务负责的 (generation, PID)，进程退出后的状态变化由监控任务处理
+    supervised: Arc<Mutex<Option<(u64, u32)>>>,
+    // 记录当前内核进程的PID文件
+    pid_file: PathBuf,
+    config_path: PathBuf,
+    // 内核进入运行状态的时刻，用于计算运行时长
+    started_at: Arc<Mutex<Option<Instant>>>,
+    metrics: Arc<Mutex<MetricsRecorder>>,
+    metrics_events: broadcast::Sender<KernelMetrics>,
+}
+
+// 就绪检查的结果
+enum Readiness {
+    Ready,
+    Exited(String),
+    TimedOut,
+}
+
+impl Default for ProcessManager {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl ProcessManager {
     pub fn new() -> Self {
+        Self::with_runner(runner::default_runner(), ProcessConfig::default())
+    }
+
+    // 使用指定的系统操作实现和进程配置创建管理器
+    pub fn with_runner(runner: Arc<dyn KernelRunner>, config: ProcessConfig) -> Self {
         Self {
             process_info: Arc::new(RwLock::new(ProcessInfo {
                 pid: None,
@@ -23,67 +78,111 @@ impl ProcessManager {
                 last_error: None,
             })),
             child_process: Arc::new(RwLock::new(None)),
+            runner,
+            config,
+            output: Arc::new(Mutex::new(KernelOutput::new(paths::get_kernel_log_path()))),
+            generation: Arc::new(AtomicU64::new(0)),
+            restart_attempts: Arc::new(AtomicU32::new(0)),
+            crashes: Arc::new(Mutex::new(VecDeque::new())),
+            crash_events: broadcast::channel(16).0,
+            status_events: broadcast::channel(64).0,
+            exit_watch: Arc::new(Mutex::new(None)),
+            supervised: Arc::new(Mutex::new(None)),
+            pid_file: paths::get_kernel_pid_path(),
+            config_path: paths::get_config_path(),
+            started_at: Arc::new(Mutex::new(None)),
+            metrics: Arc::new(Mutex::new(MetricsRecorder::new(process::METRICS_HISTORY))),
+            metrics_events: broadcast::channel(16).0,
         }
     }
 
-    // 获取进程状态
-    pub async fn get_status(&self) -> ProcessInfo {
-        self.process_info.read().await.clone()
+    // 获取最近的内核输出
+    pub fn get_output(&self, lines: usize) -> Vec<String> {
+        self.output.lock().unwrap().tail(lines)
     }
 
-    // 检查是否存在其他 sing-box 进程
-    #[allow(dead_code)]
-    async fn check_other_sing_box_process(&self) -> Option<u32> {
-        // 获取自己的PID以排除
-        let self_pid = {
-            let info = self.process_info.read().await;
-            info.pid
-        };
-        
-        match std::process::Command::new("tasklist")
-            .arg("/FI")
-            .arg("IMAGENAME eq sing-box.exe")
-            .arg("/FO")
-            .arg("CSV")
-            .arg("/NH")
-            .creation_flags(process::CREATE_NO_WINDOW)
-            .output()
-        {
-            Ok(output) => {
-                let output_str = String::from_utf8_lossy(&output.stdout);
-                if output_str.trim().is_empty() {
-                    return None; // 没有sing-box进程
-                }
-                
-                // 解析所有sing-box进程的PID
-                for line in output_str.lines() {
-                    let parts: Vec<&str> = line.split(',').collect();
-                    if parts.len() >= 2 {
-                        // 提取PID并转换为u32
-                        if let Ok(pid) = parts[1].trim_matches('"').parse::<u32>() {
-                            // 排除自己的PID
-                            if self_pid != Some(pid) {
-                                info!("发现其他sing-box进程: PID={}", pid);
-                                return Some(pid);
-                            }
-                        }
-                    }
-                }
-                None
-            }
-            Err(e) => {
-                error!("检查其他sing-box进程失败: {}", e);
-                None
-            }
+    // 获取最近的崩溃记录，最新的在后
+    pub fn get_crashes(&self) -> Vec<CrashRecord> {
+        self.crashes.lock().unwrap().iter().cloned().collect()
+    }
+
+    // 订阅崩溃事件
+    pub fn subscribe_crashes(&self) -> broadcast::Receiver<CrashRecord> {
+        self.crash_events.subscribe()
+    }
+
+    // 订阅资源占用采样
+    pub fn subscribe_metrics(&self) -> broadcast::Receiver<KernelMetrics> {
+        self.metrics_events.subscribe()
+    }
+
+    // 获取资源占用的历史采样，最新的在后
+    pub fn get_metrics_history(&self) -> Vec<KernelMetrics> {
+        self.metrics.lock().unwrap().history()
+    }
+
+    // 采样内核的资源占用，内核未运行时返回空
+    pub async fn sample_metrics(&self) -> Option<KernelMetrics> {
+        let info = self.get_status().await;
+        if info.status != ProcessStatus::Running {
+            return None;
+        }
+        let pid = info.pid?;
+
+        // 读取系统信息是阻塞操作
+        let runner = self.runner.clone();
+        let stats = tokio::task::spawn_blocking(move || runner.process_stats(pid))
+            .await
+            .ok()??;
+        let endpoints = KernelEndpoints::from_config(&self.config_path);
+        let connections = self.runner.connection_count(&endpoints).await;
+
+        let uptime_secs = self
+            .started_at
+            .lock()
+            .unwrap()
+            .map(|started| started.elapsed().as_secs())
+            .unwrap_or(0);
+        let sample = self
+            .metrics
+            .lock()
+            .unwrap()
+            .record(pid, &stats, connections, uptime_secs, Instant::now());
+
+        // 没有订阅者时发送失败，可以忽略
+        let _ = self.metrics_events.send(sample.clone());
+        Some(sample)
+    }
+
+    // 订阅进程状态变化
+    pub fn subscribe_status(&self) -> broadcast::Receiver<StatusEvent> {
+        self.status_events.subscribe()
+    }
+
+    // 修改进程信息，状态或PID变化时通知订阅者
+    async fn update_info<F: FnOnce(&mut ProcessInfo)>(&self, f: F) {
+        let mut info = self.process_info.write().await;
+        let before = (info.status.clone(), info.pid);
+        f(&mut info);
+        if before != (info.status.clone(), info.pid) {
+            // 没有订阅者时发送失败，可以忽略
+            let _ = self.status_events.send(StatusEvent::from_info(&info));
         }
     }
 
+    // 获取进程状态
+    pub async fn get_status(&self) -> ProcessInfo {
+        self.process_info.read().await.clone()
+    }
+
     // 重置进程状态
     async fn reset_process_state(&self) {
-        let mut info = self.process_info.write().await;
-        info.status = ProcessStatus::Stopped;
-        info.pid = None;
-        info.last_error = None;
+        self.update_info(|info| {
+            info.status = ProcessStatus::Stopped;
+            info.pid = None;
+            info.last_error = None;
+        })
+        .await;
 
         let mut process = self.child_process.write().await;
         *process = None;
@@ -115,128 +214,180 @@ impl ProcessManager {
             self.reset_process_state().await;
         }
 
-        // 检查是否有其他sing-box进程在运行（通过进程名称匹配）
-        if self.is_process_running_by_name("sing-box.exe").await {
-            info!("检测到其他sing-box进程正在运行，尝试强制停止所有实例");
-            if let Err(e) = self.kill_process_by_name("sing-box.exe").await {
-                warn!("无法停止部分sing-box进程: {}", e);
-            }
-            
-            // 等待进程完全停止
-            sleep(Duration::from_secs(1)).await;
-            
-            // 再次检查进程是否已全部停止
-            if self.is_process_running_by_name("sing-box.exe").await {
-                warn!("仍有sing-box进程在运行，将继续尝试启动");
-            }
-        }
+        // 停止本应用之前启动且遗留下来的内核，其他程序的sing-box进程不做处理
+        self.stop_leftover_kernels().await;
 
         // 检查配置文件
         self.check_config().await?;
 
+        // 检查配置中的端口是否被占用
+        self.check_ports().await?;
+
         Ok(())
     }
 
-    // 通过进程名称检查进程是否在运行
-    async fn is_process_running_by_name(&self, process_name: &str) -> bool {
-        let query = format!("IMAGENAME eq {}", process_name);
-        
-        match std::process::Command::new("tasklist")
-            .arg("/FI")
-            .arg(query)
-            .arg("/FO")
-            .arg("CSV")
-            .arg("/NH")
-            .creation_flags(process::CREATE_NO_WINDOW)
-            .output()
-        {
-            Ok(output) => {
-                let output_str = String::from_utf8_lossy(&output.stdout).trim().to_string();
-                !output_str.is_empty() && output_str.contains(process_name)
+    // 查找遗留的内核，枚举进程和读取进程信息是阻塞操作
+    async fn find_leftover_kernels(&self) -> Vec<u32> {
+        let runner = self.runner.clone();
+        let pid_file = self.pid_file.clone();
+        tokio::task::spawn_blocking(move || leftover_kernels(runner.as_ref(), &pid_file))
+            .await
+            .unwrap_or_default()
+    }
+
+    // PID文件记录的进程是否仍在运行，PID被其他进程复用时返回 false
+    async fn is_recorded_process(&self, record: &KernelPidFile) -> bool {
+        let runner = self.runner.clone();
+        let record = record.clone();
+        tokio::task::spawn_blocking(move || is_recorded_process(runner.as_ref(), &record))
+            .await
+            .unwrap_or(false)
+    }
+
+    // 停止遗留的内核进程，先请求正常退出，超时后强制结束
+    async fn stop_leftover_kernels(&self) {
+        for pid in self.find_leftover_kernels().await {
+            info!("停止遗留的内核进程 (PID: {})", pid);
+            let timeout = Duration::from_secs(self.config.graceful_timeout);
+            if self.signal_process(pid, false).await.is_ok() && self.poll_until_exited(pid, timeout).await {
+                continue;
             }
-            Err(e) => {
-                error!("检查进程名称失败: {}", e);
-                false
+            if let Err(e) = self.signal_process(pid, true).await {
+                warn!("无法停止遗留的内核进程 {}: {}", pid, e);
+            } else if !self.poll_until_exited(pid, Duration::from_secs(2)).await {
+                warn!("遗留的内核进程 {} 仍在运行，将继续尝试启动", pid);
             }
         }
+        pid_file::remove(&self.pid_file);
     }
 
-    // 通过进程名称终止所有匹配的进程
-    async fn kill_process_by_name(&self, process_name: &str) -> std::io::Result<()> {
-        // 使用taskkill /IM 命令强制终止所有匹配的进程
-        let output = std::process::Command::new("taskkill")
-            .arg("/F") // 强制终止
-            .arg("/IM")
-            .arg(process_name)
-            .creation_flags(process::CREATE_NO_WINDOW)
-            .output()?;
-        
-        if !output.status.success() {
-            let error = String::from_utf8_lossy(&output.stderr);
-            if error.contains("没有运行的任务") {
-                // 忽略"没有运行的任务"错误，这意味着没有找到匹配的进程
+    // 接管应用上次退出时遗留的内核，成功时返回 true
+    pub async fn adopt_orphan(&self) -> bool {
+        if self._is_running(false).await {
+            return false;
+        }
+        let record = match pid_file::read(&self.pid_file) {
+            Some(record) => record,
+            None => return false,
+        };
+        if !self.is_recorded_process(&record).await {
+            pid_file::remove(&self.pid_file);
+            return false;
+        }
+
+        self.generation.fetch_add(1, Ordering::SeqCst);
+        self.restart_attempts.store(0, Ordering::SeqCst);
+        self.update_info(|info| {
+            info.pid = Some(record.pid);
+            info.status = ProcessStatus::Running;
+            info.last_error = None;
+        })
+        .await;
+        // 无法得知实际启动时间，运行时长从接管时开始计算
+        *self.started_at.lock().unwrap() = Some(Instant::now());
+        let mark = self.output.lock().unwrap().mark();
+        self.spawn_supervisor(record.pid, None, Vec::new(), mark);
+
+        info!("已接管上次运行遗留的内核进程 (PID: {})", record.pid);
+        true
+    }
+
+    // 查找配置中被占用的端口，绑定端口和查询占用进程都是阻塞操作
+    async fn find_port_conflicts(&self) -> Vec<PortConflict> {
+        let runner = self.runner.clone();
+        let config_path = self.config_path.clone();
+        tokio::task::spawn_blocking(move || port_conflicts(runner.as_ref(), &config_path))
+            .await
+            .unwrap_or_default()
+    }
+
+    // 检查端口占用，开启自动选择端口时为被占用的端口换用空闲端口
+    async fn check_ports(&self) -> Result<()> {
+        let conflicts = self.find_port_conflicts().await;
+        if conflicts.is_empty() {
+            return Ok(());
+        }
+        for conflict in &conflicts {
+            warn!("{}", conflict);
+        }
+
+        if settings_util::load_settings().auto_select_port {
+            self.reassign_ports(&conflicts).await?;
+            let remaining = self.find_port_conflicts().await;
+            if remaining.is_empty() {
                 return Ok(());
             }
-            error!("终止进程失败: {}", error);
-            return Err(std::io::Error::new(
-                std::io::ErrorKind::Other, 
-                format!("终止进程失败: {}", error)
-            ));
+            return Err(port_error(&remaining));
         }
-        
-        info!("已终止所有 {} 进程", process_name);
-        Ok(())
+        Err(port_error(&conflicts))
     }
 
-    // 检查配置文件
-    async fn check_config(&self) -> Result<()> {
-        info!("当前工作目录: {}", get_work_dir());
-        
-        // 检查配置文件是否存在
-        let config_path = paths::get_config_path();
-        if !config_path.exists() {
-            return Err(ProcessError::ConfigError(messages::ERR_CONFIG_READ_FAILED.to_string()));
-        }
+    // 为被占用的端口选择空闲端口，只能更换混合入站和 Clash API 的端口
+    async fn reassign_ports(&self, conflicts: &[PortConflict]) -> Result<()> {
+        let config = config_util::load_config(&self.config_path).map_err(ProcessError::ConfigError)?;
+        let mut used: Vec<u16> = ports::listen_ports(&config).iter().map(|l| l.port).collect();
 
-        // 验证配置文件
-        let config_str = std::fs::read_to_string(&config_path)
-            .map_err(|e| ProcessError::ConfigError(format!("{}: {}", messages::ERR_CONFIG_READ_FAILED, e)))?;
+        // 检查端口是否可用是阻塞操作
+        let runner = self.runner.clone();
+        let conflicts = conflicts.to_vec();
+        let (proxy_port, clash_api_port) = tokio::task::spawn_blocking(move || {
+            let mut proxy_port = None;
+            let mut clash_api_port = None;
+            for conflict in &conflicts {
+                let listen = &conflict.listen;
+                if let PortUsage::Inbound(_) = listen.usage {
+                    return Err(port_error(&conflicts));
+                }
 
-        // 解析JSON
-        let json_result: serde_json::Result<serde_json::Value> = serde_json::from_str(&config_str);
-        if let Err(e) = json_result {
-            return Err(ProcessError::ConfigError(format!("配置文件JSON格式错误: {}", e)));
-        }
+                let port = (1..=network::PORT_SEARCH_RANGE)
+                    .filter_map(|offset| listen.port.checked_add(offset))
+                    .find(|port| !used.contains(port) && runner.is_port_free(&listen.address, *port))
+                    .ok_or_else(|| port_error(&conflicts))?;
+                used.push(port);
+                info!("端口 {} ({}) 被占用，改用端口 {}", listen.port, listen.name, port);
 
-        // 验证配置有效性 - 使用sing-box自带的验证功能
-        let kernel_path = paths::get_kernel_path();
-        let output = std::process::Command::new(&kernel_path)
-            .arg("check")
-            .arg("-c")
-            .arg(&config_path)
-            .creation_flags(process::CREATE_NO_WINDOW)
-            .output()
-            .map_err(|e| ProcessError::ConfigError(format!("无法验证配置: {}", e)))?;
+                match listen.usage {
+                    PortUsage::MixedInbound => proxy_port = Some(port),
+                    _ => clash_api_port = Some(port),
+                }
+            }
+            Ok((proxy_port, clash_api_port))
+        })
+        .await
+        .map_err(|e| ProcessError::SystemError(e.to_string()))??;
 
-        if !output.status.success() {
-            let error_output = String::from_utf8_lossy(&output.stderr);
-            return Err(ProcessError::ConfigError(format!("配置无效: {}", error_output)));
-        }
+        config_service::reassign_ports(proxy_port, clash_api_port)
+            .await
+            .map_err(ProcessError::ConfigError)
+    }
 
-        info!("配置文件检查通过");
-        
-        // 如果输出为空，则配置有效
+    // 检查配置文件
+    async fn check_config(&self) -> Result<()> {
+        info!("当前工作目录: {}", get_work_dir());
+
+        self.runner
+            .check_config(&self.config_path)
+            .map_err(ProcessError::ConfigError)?;
+
+        info!("{}", messages::INFO_CONFIG_CHECK_PASSED);
         Ok(())
     }
 
     // 启动进程
     pub async fn start(&self) -> Result<()> {
+        self.generation.fetch_add(1, Ordering::SeqCst);
+        self.restart_attempts.store(0, Ordering::SeqCst);
+        self.launch().await
+    }
+
+    // 启动内核并等待就绪，成功后由后台任务监控进程退出
+    async fn launch(&self) -> Result<()> {
         // 更新状态为启动中
-        {
-            let mut info = self.process_info.write().await;
+        self.update_info(|info| {
             info.status = ProcessStatus::Starting;
             info.last_error = None;
-        }
+        })
+        .await;
 
         // 启动前检查
         if let Err(e) = self.pre_start_check().await {
@@ -249,14 +400,8 @@ impl ProcessManager {
         let kernel_path = paths::get_kernel_path();
 
         // 启动进程
-        let child = match Command::new(kernel_path.to_str().unwrap())
-            .arg("run")
-            .arg("-D")
-            .arg(kernel_work_dir.to_str().unwrap())
-            .creation_flags(process::CREATE_NO_WINDOW)
-            .spawn()
-        {
-            Ok(child) => child,
+        let mut kernel = match self.runner.spawn(&kernel_path, &kernel_work_dir) {
+            Ok(kernel) => kernel,
             Err(e) => {
                 let err = ProcessError::StartFailed(format!("启动失败: {}", e));
                 self.handle_error(err.clone()).await?;
@@ -265,24 +410,316 @@ impl ProcessManager {
         };
 
         // 更新进程信息
-        {
-            let mut info = self.process_info.write().await;
-            info.pid = Some(child.id().unwrap_or(0));
+        self.update_info(|info| {
+            info.pid = Some(kernel.pid);
             info.status = ProcessStatus::Starting;
+        })
+        .await;
+        pid_file::write(
+            &self.pid_file,
+            &KernelPidFile {
+                pid: kernel.pid,
+                identity: self.runner.process_identity(kernel.pid).unwrap_or_default(),
+            },
+        );
+
+        // 读取内核输出，并保存子进程
+        let mark = self.output.lock().unwrap().mark();
+        let readers = self.capture_output(&mut kernel);
+        {
+            let mut process = self.child_process.write().await;
+            *process = kernel.child;
+        }
+        info!("{}", messages::INFO_PROCESS_STARTED);
+
+        // 等待内核开始提供服务
+        let endpoints = KernelEndpoints::from_config(&self.config_path);
+        let reason = match self.wait_until_ready(kernel.pid, &endpoints).await {
+            Readiness::Ready => {
+                self.update_info(|info| info.status = ProcessStatus::Running).await;
+                *self.started_at.lock().unwrap() = Some(Instant::now());
+                info!("内核已就绪 (PID: {})", kernel.pid);
+
+                let child = self.child_process.write().await.take();
+                self.spawn_supervisor(kernel.pid, child, readers, mark);
+                return Ok(());
+            }
+            Readiness::Exited(status) => format!("内核启动后退出 ({})", status),
+            Readiness::TimedOut => {
+                // 未就绪的进程不再保留
+                if let Err(e) = self.signal_process(kernel.pid, true).await {
+                    warn!("结束未就绪的内核进程失败: {}", e);
+                }
+                format!("内核在 {} 秒内未就绪", self.config.ready_timeout)
+            }
+        };
+
+        // 等待剩余输出读取完成，附加到错误信息中
+        for reader in readers {
+            let _ = tokio::time::timeout(Duration::from_secs(1), reader).await;
         }
+        let err = ProcessError::StartFailed(with_output_tail(reason, &self.output, mark));
 
-        // 保存子进程
         {
             let mut process = self.child_process.write().await;
-            *process = Some(child);
+            *process = None;
         }
+        pid_file::remove(&self.pid_file);
+        self.handle_error(err.clone()).await?;
+        Err(err)
+    }
 
-        info!("{}", messages::INFO_PROCESS_STARTED);
-        Ok(())
+    // 轮询内核状态，直到就绪、退出或超时
+    async fn wait_until_ready(&self, pid: u32, endpoints: &KernelEndpoints) -> Readiness {
+        let deadline = std::time::Instant::now() + Duration::from_secs(self.config.ready_timeout);
+
+        loop {
+            if let Some(status) = self.check_exited(pid).await {
+                return Readiness::Exited(status);
+            }
+
+            if self.runner.is_ready(endpoints).await {
+                return Readiness::Ready;
+            }
+
+            if std::time::Instant::now() >= deadline {
+                return Readiness::TimedOut;
+            }
+            sleep(Duration::from_millis(process::READY_POLL_INTERVAL_MS)).await;
+        }
+    }
+
+    // 检查内核是否已经退出，返回退出状态
+    async fn check_exited(&self, pid: u32) -> Option<String> {
+        let mut process = self.child_process.write().await;
+        match process.as_mut() {
+            Some(child) => match child.try_wait() {
+                Ok(Some(status)) => Some(status.to_string()),
+                Ok(None) => None,
+                Err(e) => Some(e.to_string()),
+            },
+            None if !self.runner.process_exists(pid) => Some("进程不存在".to_string()),
+            None => None,
+        }
+    }
+
+    // 启动后台监控任务
+    fn spawn_supervisor(
+        &self,
+        pid: u32,
+        child: Option<tokio::process::Child>,
+        readers: Vec<JoinHandle<()>>,
+        mark: u64,
+    ) {
+        let manager = self.clone();
+        let generation = self.generation.load(Ordering::SeqCst);
+
+        let exited = match child {
+            Some(_) => {
+                let (sender, receiver) = watch::channel(false);
+                *self.exit_watch.lock().unwrap() = Some(receiver);
+                Some(sender)
+            }
+            None => {
+                *self.exit_watch.lock().unwrap() = None;
+                None
+            }
+        };
+
+        *self.supervised.lock().unwrap() = Some((generation, pid));
+        tokio::spawn(async move {
+            manager.clone().supervise(pid, child, exited, readers, mark, generation).await;
+            // 自动重启后由新的监控任务负责，只清除自己的记录
+            let mut supervised = manager.supervised.lock().unwrap();
+            if *supervised == Some((generation, pid)) {
+                *supervised = None;
+            }
+        });
+    }
+
+    // 等待内核退出，意外退出时记录崩溃并自动重启
+    async fn supervise(
+        self,
+        pid: u32,
+        child: Option<tokio::process::Child>,
+        exited: Option<watch::Sender<bool>>,
+        readers: Vec<JoinHandle<()>>,
+        mark: u64,
+        generation: u64,
+    ) {
+        let started = Instant::now();
+        let (exit_code, status) = match self.wait_for_exit(pid, child, generation).await {
+            Some(exit) => exit,
+            None => return,
+        };
+        if let Some(exited) = exited {
+            let _ = exited.send(true);
+        }
+        for reader in readers {
+            let _ = tokio::time::timeout(Duration::from_secs(1), reader).await;
+        }
+
+        // 手动停止或重新启动导致的退出不处理
+        if self.generation.load(Ordering::SeqCst) != generation {
+            return;
+        }
+        pid_file::remove(&self.pid_file);
+
+        // 稳定运行超过一个健康检查周期后重新计算连续崩溃次数
+        if started.elapsed() >= Duration::from_secs(self.config.health_check_interval) {
+            self.restart_attempts.store(0, Ordering::SeqCst);
+        }
+
+        let output = self
+            .output
+            .lock()
+            .unwrap()
+            .lines_since(mark, process::OUTPUT_TAIL_LINES);
+        self.recover(generation, format!("内核意外退出 ({})", status), exit_code, output)
+            .await;
+    }
+
+    // 等待进程退出，返回退出码和退出状态的描述
+    // 没有进程句柄时按健康检查间隔轮询，期间手动停止或重启则返回空
+    async fn wait_for_exit(
+        &self,
+        pid: u32,
+        child: Option<tokio::process::Child>,
+        generation: u64,
+    ) -> Option<(Option<i32>, String)> {
+        match child {
+            Some(mut child) => match child.wait().await {
+                Ok(status) => Some((status.code(), status.to_string())),
+                Err(e) => Some((None, e.to_string())),
+            },
+            None => {
+                let interval = Duration::from_secs(self.config.health_check_interval.max(1));
+                while self.check_process_exists(Some(pid)).await {
+                    sleep(interval).await;
+                    if self.generation.load(Ordering::SeqCst) != generation {
+                        return None;
+                    }
+                }
+                Some((None, "进程不存在".to_string()))
+            }
+        }
+    }
+
+    // 记录崩溃并按退避时间重启，超过最大重启次数后停止重试
+    async fn recover(
+        &self,
+        generation: u64,
+        mut reason: String,
+        mut exit_code: Option<i32>,
+        mut output: Vec<String>,
+    ) {
+        loop {
+            let attempt = self.restart_attempts.fetch_add(1, Ordering::SeqCst) + 1;
+            let will_restart = attempt <= self.config.max_restart_attempts;
+
+            let message = if will_restart {
+                reason.clone()
+            } else {
+                format!("内核连续崩溃 {} 次，已停止自动重启: {}", attempt, reason)
+            };
+            let message = if output.is_empty() {
+                message
+            } else {
+                format!("{}\n{}", message, output.join("\n"))
+            };
+            error!("{}", message);
+            self.update_info(|info| {
+                info.status = ProcessStatus::Failed(message.clone());
+                info.last_error = Some(message);
+                info.pid = None;
+            })
+            .await;
+
+            self.record_crash(CrashRecord {
+                time: SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .map(|d| d.as_secs())
+                    .unwrap_or_default(),
+                exit_code,
+                reason: reason.clone(),
+                output: std::mem::take(&mut output),
+                attempt,
+                will_restart,
+            });
+            if !will_restart {
+                return;
+            }
+
+            // 退避时间按连续崩溃次数翻倍
+            let delay = self
+                .config
+                .restart_delay
+                .saturating_mul(1 << (attempt - 1).min(16))
+                .min(process::RESTART_BACKOFF_MAX);
+            warn!("{} 秒后第 {} 次自动重启内核", delay, attempt);
+            sleep(Duration::from_secs(delay)).await;
+
+            if self.generation.load(Ordering::SeqCst) != generation {
+                return;
+            }
+            match self.launch().await {
+                Ok(_) => {
+                    info!("内核已自动重启");
+                    return;
+                }
+                Err(e) => {
+                    reason = e.to_string();
+                    exit_code = None;
+                }
+            }
+        }
+    }
+
+    // 保存崩溃记录并通知订阅者
+    fn record_crash(&self, record: CrashRecord) {
+        {
+            let mut crashes = self.crashes.lock().unwrap();
+            if crashes.len() >= process::MAX_CRASH_RECORDS {
+                crashes.pop_front();
+            }
+            crashes.push_back(record.clone());
+        }
+        // 没有订阅者时发送失败，可以忽略
+        let _ = self.crash_events.send(record);
+    }
+
+    // 开始读取内核的标准输出和错误输出
+    fn capture_output(&self, kernel: &mut runner::KernelChild) -> Vec<JoinHandle<()>> {
+        let mut readers = Vec::new();
+        if let Some(child) = kernel.child.as_mut() {
+            if let Some(stdout) = child.stdout.take() {
+                readers.push(self.spawn_reader(stdout));
+            }
+            if let Some(stderr) = child.stderr.take() {
+                readers.push(self.spawn_reader(stderr));
+            }
+        }
+        readers
+    }
+
+    // 按行读取输出
+    fn spawn_reader<T>(&self, stream: T) -> JoinHandle<()>
+    where
+        T: AsyncRead + Unpin + Send + 'static,
+    {
+        let output = self.output.clone();
+        tokio::spawn(async move {
+            let mut lines = BufReader::new(stream).lines();
+            while let Ok(Some(line)) = lines.next_line().await {
+                output.lock().unwrap().push(line);
+            }
+        })
     }
 
     // 停止进程
     pub async fn stop(&self) -> Result<()> {
+        self.generation.fetch_add(1, Ordering::SeqCst);
+
         // 检查进程状态
         let status = self.get_status().await.status;
         if matches!(status, ProcessStatus::Stopped) {
@@ -290,20 +727,20 @@ impl ProcessManager {
         }
 
         // 更新状态为停止中
-        {
-            let mut info = self.process_info.write().await;
-            info.status = ProcessStatus::Stopping;
+        self.update_info(|info| info.status = ProcessStatus::Stopping).await;
+
+        // 首先尝试优雅地停止进程，让内核保存缓存并清理 TUN
+        match self.graceful_stop().await {
+            Ok(_) => {}
+            // 进程已经退出（例如自动重启失败后），只需要更新状态
+            Err(ProcessError::NotRunning) => {}
+            // 如果优雅停止失败，则强制终止
+            Err(e) => {
+                warn!("{}，尝试强制终止", e);
+                self.force_stop().await?;
+            }
         }
 
-        // 首先尝试优雅地停止进程
-        // let stop_result = self.graceful_stop().await;
-        
-        // 如果优雅停止失败，则强制终止
-        // if stop_result.is_err() {
-            warn!("进程停止超时，尝试强制终止");
-            self.force_stop().await?;
-        // }
-        
         // 关闭系统代理
         if let Err(e) = disable_system_proxy() {
             warn!("关闭系统代理失败: {}", e);
@@ -312,38 +749,17 @@ impl ProcessManager {
         }
 
         // 更新进程状态
-        {
-            let mut info = self.process_info.write().await;
+        self.update_info(|info| {
             info.status = ProcessStatus::Stopped;
             info.pid = None;
-        }
+        })
+        .await;
+        pid_file::remove(&self.pid_file);
 
         info!("{}", messages::INFO_PROCESS_STOPPED);
         Ok(())
     }
 
-    // 发送停止信号
-    #[allow(dead_code)]
-    fn send_signal(&self, pid: u32) -> std::io::Result<()> {
-        std::process::Command::new("taskkill")
-            .arg("/PID")
-            .arg(pid.to_string())
-            .creation_flags(process::CREATE_NO_WINDOW)
-            .output()?;
-        Ok(())
-    }
-
-    // 强制结束进程
-    fn kill_process(&self, pid: u32) -> std::io::Result<()> {
-        std::process::Command::new("taskkill")
-            .arg("/F")
-            .arg("/PID")
-            .arg(pid.to_string())
-            .creation_flags(process::CREATE_NO_WINDOW)
-            .output()?;
-        Ok(())
-    }
-
     // 重启进程 强制停止
     pub async fn restart(&self) -> Result<()> {
         self.stop().await?;
@@ -355,15 +771,17 @@ impl ProcessManager {
 
     // 错误处理
     async fn handle_error(&self, err: ProcessError) -> Result<()> {
-        let mut info = self.process_info.write().await;
-        info.status = ProcessStatus::Failed(err.to_string());
-        info.last_error = Some(err.to_string());
+        self.update_info(|info| {
+            info.status = ProcessStatus::Failed(err.to_string());
+            info.last_error = Some(err.to_string());
+            info.pid = None;
+        })
+        .await;
         error!("进程错误: {}", err);
         Ok(())
     }
 
     // 优雅停止进程
-    #[allow(dead_code)]
     async fn graceful_stop(&self) -> Result<()> {
         let pid = {
             let info = self.process_info.read().await;
@@ -371,20 +789,13 @@ impl ProcessManager {
         };
 
         // 尝试发送正常停止信号
-        if let Err(e) = self.send_signal(pid) {
+        if let Err(e) = self.signal_process(pid, false).await {
             return Err(ProcessError::StopFailed(format!("发送停止信号失败: {}", e)));
         }
 
-        // 等待进程停止的超时时间
-        let timeout = Duration::from_secs(process::GRACEFUL_TIMEOUT);
-        let start = std::time::Instant::now();
-
         // 等待进程停止
-        while self.check_process_exists(Some(pid)).await {
-            if start.elapsed() > timeout {
-                return Err(ProcessError::StopFailed("进程停止超时".to_string()));
-            }
-            sleep(Duration::from_millis(100)).await;
+        if !self.wait_for_stop(pid, Duration::from_secs(self.config.graceful_timeout)).await {
+            return Err(ProcessError::StopFailed("进程停止超时".to_string()));
         }
 
         Ok(())
@@ -398,55 +809,65 @@ impl ProcessManager {
         };
 
         // 强制结束进程
-        if let Err(e) = self.kill_process(pid) {
+        if let Err(e) = self.signal_process(pid, true).await {
             return Err(ProcessError::StopFailed(format!("强制停止失败: {}", e)));
         }
 
         // 短暂等待确保进程已终止
-        sleep(Duration::from_millis(500)).await;
-        
-        // 检查进程是否仍存在
-        if self.check_process_exists(Some(pid)).await {
+        if !self.wait_for_stop(pid, Duration::from_secs(2)).await {
             return Err(ProcessError::StopFailed("强制停止失败，进程仍在运行".to_string()));
         }
 
         Ok(())
     }
 
+    // 等待进程退出，超时返回 false
+    // 有子进程句柄时等待监控任务的退出通知，否则轮询进程是否存在
+    async fn wait_for_stop(&self, pid: u32, timeout: Duration) -> bool {
+        let exit_watch = self.exit_watch.lock().unwrap().clone();
+        if let Some(mut exited) = exit_watch {
+            let result = tokio::time::timeout(timeout, exited.wait_for(|exited| *exited))
+                .await
+                .map(|r| r.is_ok());
+            return match result {
+                Ok(true) => true,
+                // 监控任务已结束，以进程实际状态为准
+                Ok(false) => !self.check_process_exists(Some(pid)).await,
+                Err(_) => false,
+            };
+        }
+
+        self.poll_until_exited(pid, timeout).await
+    }
+
+    // 等待进程退出，超时返回 false
+    // 系统调用会阻塞，在阻塞线程中执行
+    async fn poll_until_exited(&self, pid: u32, timeout: Duration) -> bool {
+        let runner = self.runner.clone();
+        tokio::task::spawn_blocking(move || runner.wait_for_exit(pid, timeout))
+            .await
+            .unwrap_or(false)
+    }
+
     // 检查进程是否存在
     async fn check_process_exists(&self, pid: Option<u32>) -> bool {
-        if let Some(pid) = pid {
-            // 使用具体的PID格式化查询，确保准确匹配
-            let query = format!("PID eq {}", pid);
-            
-            match std::process::Command::new("tasklist")
-                .arg("/FI")
-                .arg(query)
-                .arg("/FO")
-                .arg("CSV")
-                .arg("/NH") // 不显示标题行
-                .creation_flags(process::CREATE_NO_WINDOW)
-                .output()
-            {
-                Ok(output) => {
-                    let output_str = String::from_utf8_lossy(&output.stdout).trim().to_string();
-                    // 检查输出是否包含PID
-                    // 输出格式应该是 "sing-box.exe","PID",...
-                    if output_str.is_empty() {
-                        return false;
-                    }
-                    
-                    // 更严格地检查PID是否匹配
-                    output_str.contains(&format!(",\"{}\"", pid))
-                }
-                Err(_) => {
-                    error!("查询进程状态失败");
-                    false
-                }
-            }
-        } else {
-            false
-        }
+        let pid = match pid {
+            Some(pid) => pid,
+            None => return false,
+        };
+        let runner = self.runner.clone();
+        tokio::task::spawn_blocking(move || runner.process_exists(pid))
+            .await
+            .unwrap_or(false)
+    }
+
+    // 请求进程退出，force 为 true 时强制结束
+    // Windows 下需要附加控制台或调用 taskkill，在阻塞线程中执行
+    async fn signal_process(&self, pid: u32, force: bool) -> std::io::Result<()> {
+        let runner = self.runner.clone();
+        tokio::task::spawn_blocking(move || if force { runner.kill(pid) } else { runner.terminate(pid) })
+            .await
+            .unwrap_or_else(|e| Err(std::io::Error::other(e)))
     }
 
     // 内部是否运行中检查函数，可以强制检查实际进程
@@ -463,9 +884,9 @@ impl ProcessManager {
             return false;
         }
 
-        // 如果没有PID，说明进程未运行
+        // 如果没有PID，说明进程未运行（启动中尚未创建进程时也没有PID，不需要重置）
         if info.pid.is_none() {
-            if status_running {
+            if info.status == ProcessStatus::Running {
                 // 状态不一致，需要重置
                 drop(info); // 释放读锁
                 self.reset_process_state().await;
@@ -479,6 +900,12 @@ impl ProcessManager {
         let exists = self.check_process_exists(Some(pid)).await;
         
         if !exists && status_running {
+            // 监控任务正在处理该进程的退出，由它更新状态并决定是否重启
+            let generation = self.generation.load(Ordering::SeqCst);
+            if *self.supervised.lock().unwrap() == Some((generation, pid)) {
+                return false;
+            }
+
             // 进程不存在但状态显示运行中，重置状态
             drop(info); // 释放读锁
             self.reset_process_state().await;
@@ -491,3 +918,523 @@ impl ProcessManager {
     }
 }
 
+// 查找遗留的内核：PID文件记录的进程，以及由本应用内核文件启动的进程
+fn leftover_kernels(runner: &dyn KernelRunner, pid_file: &Path) -> Vec<u32> {
+    let kernel_path = paths::get_kernel_path();
+    let mut leftovers = Vec::new();
+
+    if let Some(record) = pid_file::read(pid_file) {
+        if is_recorded_process(runner, &record) {
+            leftovers.push(record.pid);
+        }
+    }
+
+    for pid in runner.find_by_name(process::KERNEL_EXE_NAME) {
+        if leftovers.contains(&pid) {
+            continue;
+        }
+        match runner.process_identity(pid) {
+            Some(identity) if identity.is_executable(&kernel_path) => leftovers.push(pid),
+            _ => info!("检测到其他程序启动的sing-box进程 (PID: {})，不做处理", pid),
+        }
+    }
+    leftovers
+}
+
+fn is_recorded_process(runner: &dyn KernelRunner, record: &KernelPidFile) -> bool {
+    runner
+        .process_identity(record.pid)
+        .map(|identity| identity.matches(&record.identity))
+        .unwrap_or(false)
+}
+
+// 查找配置中被占用的端口，端口的占用进程只查询一次
+fn port_conflicts(runner: &dyn KernelRunner, config_path: &Path) -> Vec<PortConflict> {
+    let config = match config_util::load_config(config_path) {
+        Ok(config) => config,
+        Err(_) => return Vec::new(),
+    };
+
+    let busy: Vec<_> = ports::listen_ports(&config)
+        .into_iter()
+        .filter(|listen| !runner.is_port_free(&listen.address, listen.port))
+        .collect();
+    if busy.is_empty() {
+        return Vec::new();
+    }
+
+    let owners = runner.port_owners(&busy.iter().map(|listen| listen.port).collect::<Vec<_>>());
+    busy.into_iter()
+        .map(|listen| {
+            let owner_pid = owners.get(&listen.port).copied();
+            let owner_name = owner_pid
+                .and_then(|pid| runner.process_identity(pid))
+                .and_then(|identity| identity.exe_path)
+                .and_then(|path| path.file_name().map(|n| n.to_string_lossy().into_owned()));
+            PortConflict {
+                listen,
+                owner_pid,
+                owner_name,
+            }
+        })
+        .collect()
+}
+
+// 端口占用的错误信息
+fn port_error(conflicts: &[PortConflict]) -> ProcessError {
+    let messages: Vec<String> = conflicts.iter().map(|c| c.to_string()).collect();
+    ProcessError::NetworkError(messages.join("; "))
+}
+
+// 在错误信息后附加本次启动以来的内核输出
+fn with_output_tail(message: String, output: &Mutex<KernelOutput>, mark: u64) -> String {
+    let tail = output.lock().unwrap().lines_since(mark, process::OUTPUT_TAIL_LINES);
+    if tail.is_empty() {
+        message
+    } else {
+        format!("{}\n{}", message, tail.join("\n"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::process::runner::fake::FakeRunner;
+    use crate::process::runner::{ProcessIdentity, ProcessStats};
+    use std::path::Path;
+
+    // 每个测试使用单独的临时文件
+    fn temp_file(extension: &str) -> PathBuf {
+        static COUNTER: AtomicU32 = AtomicU32::new(0);
+        let n = COUNTER.fetch_add(1, Ordering::SeqCst);
+        std::env::temp_dir().join(format!("kernel-{}-{}.{}", std::process::id(), n, extension))
+    }
+
+    fn manager() -> (ProcessManager, Arc<FakeRunner>) {
+        let runner = Arc::new(FakeRunner::new());
+        let config = ProcessConfig {
+            graceful_timeout: 1,
+            health_check_interval: 1,
+            max_restart_attempts: 2,
+            restart_delay: 0,
+            ready_timeout: 1,
+        };
+        let mut manager = ProcessManager::with_runner(runner.clone(), config);
+        manager.pid_file = temp_file("pid");
+        manager.config_path = temp_file("json");
+        (manager, runner)
+    }
+
+    #[tokio::test]
+    async fn start_and_stop() {
+        let (manager, runner) = manager();
+
+        manager.start().await.unwrap();
+        let pid = manager.get_status().await.pid.unwrap();
+        assert!(manager.is_running().await);
+        assert_eq!(manager.get_status().await.status, ProcessStatus::Running);
+        assert_eq!(runner.running_pids(), vec![pid]);
+
+        manager.stop().await.unwrap();
+        let info = manager.get_status().await;
+        assert_eq!(info.status, ProcessStatus::Stopped);
+        assert_eq!(info.pid, None);
+        assert!(runner.running_pids().is_empty());
+        assert!(runner.calls().contains(&format!("terminate {}", pid)));
+        assert!(!runner.calls().contains(&format!("kill {}", pid)));
+    }
+
+    #[tokio::test]
+    async fn stop_escalates_to_kill_after_timeout() {
+        let (manager, runner) = manager();
+        runner.set_ignore_terminate(true);
+
+        manager.start().await.unwrap();
+        let pid = manager.get_status().await.pid.unwrap();
+        manager.stop().await.unwrap();
+
+        let calls = runner.calls();
+        let terminate = calls.iter().position(|c| *c == format!("terminate {}", pid)).unwrap();
+        let kill = calls.iter().position(|c| *c == format!("kill {}", pid)).unwrap();
+        assert!(terminate < kill);
+        assert!(runner.running_pids().is_empty());
+        assert_eq!(manager.get_status().await.status, ProcessStatus::Stopped);
+    }
+
+    #[tokio::test]
+    async fn stop_after_giving_up_resets_state() {
+        let (manager, runner) = manager();
+        runner.set_spawn_error(Some("拒绝访问"));
+        let _ = manager.start().await;
+
+        manager.stop().await.unwrap();
+        assert_eq!(manager.get_status().await.status, ProcessStatus::Stopped);
+    }
+
+    #[tokio::test]
+    async fn stop_when_stopped_is_noop() {
+        let (manager, runner) = manager();
+
+        manager.stop().await.unwrap();
+        assert!(runner.calls().is_empty());
+    }
+
+    #[tokio::test]
+    async fn restart_replaces_process() {
+        let (manager, runner) = manager();
+
+        manager.start().await.unwrap();
+        let old_pid = manager.get_status().await.pid.unwrap();
+        manager.restart().await.unwrap();
+        let new_pid = manager.get_status().await.pid.unwrap();
+
+        assert_ne!(old_pid, new_pid);
+        assert_eq!(runner.running_pids(), vec![new_pid]);
+    }
+
+    #[tokio::test]
+    async fn pre_start_check_stops_only_own_kernels() {
+        let (manager, runner) = manager();
+        let leftover = runner.add_process(&paths::get_kernel_path());
+        let other_kernel = runner.add_process(&Path::new("/opt/other").join(process::KERNEL_EXE_NAME));
+        let unrelated = runner.add_process(Path::new("/usr/bin/other"));
+
+        manager.start().await.unwrap();
+        let pid = manager.get_status().await.pid.unwrap();
+
+        assert!(!runner.process_exists(leftover));
+        assert!(runner.process_exists(other_kernel));
+        assert!(runner.process_exists(unrelated));
+        assert!(runner.process_exists(pid));
+        assert!(runner.calls().contains(&format!("terminate {}", leftover)));
+    }
+
+    #[tokio::test]
+    async fn pid_file_tracks_running_kernel() {
+        let (manager, runner) = manager();
+
+        manager.start().await.unwrap();
+        let pid = manager.get_status().await.pid.unwrap();
+        let record = pid_file::read(&manager.pid_file).unwrap();
+        assert_eq!(record.pid, pid);
+        assert_eq!(Some(record.identity), runner.process_identity(pid));
+
+        manager.stop().await.unwrap();
+        assert!(pid_file::read(&manager.pid_file).is_none());
+    }
+
+    #[tokio::test]
+    async fn recorded_orphan_is_stopped_before_start() {
+        let (manager, runner) = manager();
+        let orphan = runner.add_process(Path::new("/moved/kernel"));
+        pid_file::write(
+            &manager.pid_file,
+            &KernelPidFile {
+                pid: orphan,
+                identity: runner.process_identity(orphan).unwrap(),
+            },
+        );
+
+        manager.start().await.unwrap();
+        assert!(!runner.process_exists(orphan));
+    }
+
+    #[tokio::test]
+    async fn reused_pid_is_left_alone() {
+        let (manager, runner) = manager();
+        let other = runner.add_process(Path::new("/usr/bin/other"));
+        pid_file::write(
+            &manager.pid_file,
+            &KernelPidFile {
+                pid: other,
+                identity: ProcessIdentity {
+                    exe_path: Some(Path::new("/usr/bin/other").to_path_buf()),
+                    start_time: Some(1),
+                },
+            },
+        );
+
+        assert!(!manager.adopt_orphan().await);
+        manager.start().await.unwrap();
+        assert!(runner.process_exists(other));
+    }
+
+    #[tokio::test]
+    async fn adopts_recorded_orphan() {
+        let (manager, runner) = manager();
+        let orphan = runner.add_process(&paths::get_kernel_path());
+        pid_file::write(
+            &manager.pid_file,
+            &KernelPidFile {
+                pid: orphan,
+                identity: runner.process_identity(orphan).unwrap(),
+            },
+        );
+
+        assert!(manager.adopt_orphan().await);
+        let info = manager.get_status().await;
+        assert_eq!(info.status, ProcessStatus::Running);
+        assert_eq!(info.pid, Some(orphan));
+
+        manager.stop().await.unwrap();
+        assert!(!runner.process_exists(orphan));
+    }
+
+    #[tokio::test]
+    async fn invalid_config_prevents_start() {
+        let (manager, runner) = manager();
+        runner.set_config_error(Some("配置无效"));
+
+        assert!(matches!(manager.start().await, Err(ProcessError::ConfigError(_))));
+        assert!(matches!(manager.get_status().await.status, ProcessStatus::Failed(_)));
+        assert!(!runner.calls().contains(&"spawn".to_string()));
+    }
+
+    #[tokio::test]
+    async fn spawn_failure_marks_failed() {
+        let (manager, runner) = manager();
+        runner.set_spawn_error(Some("拒绝访问"));
+
+        assert!(matches!(manager.start().await, Err(ProcessError::StartFailed(_))));
+        let info = manager.get_status().await;
+        assert!(matches!(info.status, ProcessStatus::Failed(_)));
+        assert!(info.last_error.unwrap().contains("拒绝访问"));
+    }
+
+    #[tokio::test]
+    async fn unsupervised_exit_resets_state() {
+        let (manager, runner) = manager();
+
+        manager.start().await.unwrap();
+        let pid = manager.get_status().await.pid.unwrap();
+        // 没有监控任务负责该进程时，由状态检查重置状态
+        *manager.supervised.lock().unwrap() = None;
+        runner.exit_process(pid);
+
+        assert!(!manager.is_running().await);
+        let info = manager.get_status().await;
+        assert_eq!(info.status, ProcessStatus::Stopped);
+        assert_eq!(info.pid, None);
+    }
+
+    #[tokio::test]
+    async fn never_ready_marks_failed_and_kills() {
+        let (manager, runner) = manager();
+        runner.set_ready(false);
+
+        let err = manager.start().await.unwrap_err();
+        assert!(matches!(err, ProcessError::StartFailed(_)));
+        assert!(err.to_string().contains("未就绪"));
+
+        let info = manager.get_status().await;
+        assert!(matches!(info.status, ProcessStatus::Failed(_)));
+        assert_eq!(info.pid, None);
+        assert!(runner.running_pids().is_empty());
+    }
+
+    #[tokio::test]
+    async fn early_exit_marks_failed() {
+        let (manager, runner) = manager();
+        runner.set_ready(false);
+        runner.set_exit_on_spawn(true);
+
+        let err = manager.start().await.unwrap_err();
+        assert!(err.to_string().contains("退出"));
+        assert!(matches!(manager.get_status().await.status, ProcessStatus::Failed(_)));
+        assert!(!runner.calls().iter().any(|c| c.starts_with("kill")));
+    }
+
+    fn spawn_count(runner: &FakeRunner) -> usize {
+        runner.calls().iter().filter(|c| c.as_str() == "spawn").count()
+    }
+
+    #[tokio::test]
+    async fn crash_triggers_restart() {
+        let (manager, runner) = manager();
+        let mut events = manager.subscribe_crashes();
+
+        manager.start().await.unwrap();
+        let pid = manager.get_status().await.pid.unwrap();
+        runner.exit_process(pid);
+
+        let record = tokio::time::timeout(Duration::from_secs(5), events.recv())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(record.attempt, 1);
+        assert!(record.will_restart);
+
+        sleep(Duration::from_millis(500)).await;
+        let info = manager.get_status().await;
+        assert_eq!(info.status, ProcessStatus::Running);
+        assert_ne!(info.pid, Some(pid));
+        assert_eq!(spawn_count(&runner), 2);
+        assert_eq!(manager.get_crashes().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn status_check_leaves_crash_to_supervisor() {
+        let (manager, runner) = manager();
+        manager.start().await.unwrap();
+        let pid = manager.get_status().await.pid.unwrap();
+        let mut statuses = manager.subscribe_status();
+        let mut crashes = manager.subscribe_crashes();
+
+        // 监控任务发现退出之前检查状态，不应报告为已停止
+        runner.exit_process(pid);
+        assert!(!manager.is_running().await);
+        assert_eq!(manager.get_status().await.pid, Some(pid));
+
+        tokio::time::timeout(Duration::from_secs(5), crashes.recv())
+            .await
+            .unwrap()
+            .unwrap();
+        let mut seen = Vec::new();
+        while let Ok(event) = statuses.try_recv() {
+            seen.push(event.status);
+        }
+        assert!(matches!(seen.first(), Some(ProcessStatus::Failed(_))));
+        assert!(!seen.contains(&ProcessStatus::Stopped));
+    }
+
+    #[tokio::test]
+    async fn gives_up_after_max_attempts() {
+        let (manager, runner) = manager();
+
+        manager.start().await.unwrap();
+        let pid = manager.get_status().await.pid.unwrap();
+        runner.set_exit_on_spawn(true);
+        runner.exit_process(pid);
+
+        sleep(Duration::from_secs(3)).await;
+        let info = manager.get_status().await;
+        match info.status {
+            ProcessStatus::Failed(reason) => assert!(reason.contains("已停止自动重启")),
+            status => panic!("unexpected status: {:?}", status),
+        }
+
+        let crashes = manager.get_crashes();
+        assert_eq!(crashes.len(), 3);
+        assert!(!crashes.last().unwrap().will_restart);
+        assert_eq!(spawn_count(&runner), 3);
+    }
+
+    #[tokio::test]
+    async fn manual_stop_does_not_restart() {
+        let (manager, runner) = manager();
+
+        manager.start().await.unwrap();
+        manager.stop().await.unwrap();
+        sleep(Duration::from_millis(1500)).await;
+
+        assert_eq!(manager.get_status().await.status, ProcessStatus::Stopped);
+        assert_eq!(spawn_count(&runner), 1);
+        assert!(manager.get_crashes().is_empty());
+    }
+
+    fn drain_statuses(events: &mut broadcast::Receiver<StatusEvent>) -> Vec<ProcessStatus> {
+        let mut statuses = Vec::new();
+        while let Ok(event) = events.try_recv() {
+            statuses.push(event.status);
+        }
+        statuses
+    }
+
+    #[tokio::test]
+    async fn status_changes_are_broadcast() {
+        let (manager, _runner) = manager();
+        let mut events = manager.subscribe_status();
+
+        manager.start().await.unwrap();
+        manager.stop().await.unwrap();
+
+        let statuses = drain_statuses(&mut events);
+        assert_eq!(
+            statuses,
+            vec![
+                ProcessStatus::Starting,
+                ProcessStatus::Starting,
+                ProcessStatus::Running,
+                ProcessStatus::Stopping,
+                ProcessStatus::Stopped,
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn failed_start_is_broadcast_with_reason() {
+        let (manager, runner) = manager();
+        runner.set_spawn_error(Some("拒绝访问"));
+        let mut events = manager.subscribe_status();
+
+        let _ = manager.start().await;
+
+        let mut last = None;
+        while let Ok(event) = events.try_recv() {
+            last = Some(event);
+        }
+        let event = last.unwrap();
+        assert!(matches!(event.status, ProcessStatus::Failed(_)));
+        assert_eq!(event.pid, None);
+        assert!(event.reason.unwrap().contains("拒绝访问"));
+        assert!(event.timestamp > 0);
+    }
+
+    #[tokio::test]
+    async fn busy_port_prevents_start() {
+        let (manager, runner) = manager();
+        std::fs::write(
+            &manager.config_path,
+            r#"{
+                "inbounds": [{"type": "http", "tag": "http-in", "listen": "127.0.0.1", "listen_port": 18080}],
+                "experimental": {"clash_api": {"external_controller": "127.0.0.1:18081"}}
+            }"#,
+        )
+        .unwrap();
+        let owner = runner.add_process(Path::new("/usr/bin/nginx"));
+        runner.set_port_busy(18080, Some(owner));
+        runner.set_port_busy(18081, None);
+
+        let err = manager.start().await.unwrap_err();
+        let message = err.to_string();
+        assert!(matches!(err, ProcessError::NetworkError(_)));
+        assert!(message.contains("18080 (http-in)"));
+        assert!(message.contains(&format!("nginx (PID: {})", owner)));
+        assert!(message.contains("18081 (Clash API)"));
+        assert!(!runner.calls().contains(&"spawn".to_string()));
+        assert_eq!(runner.calls().iter().filter(|c| *c == "port_owners").count(), 1);
+
+        let _ = std::fs::remove_file(&manager.config_path);
+    }
+
+    #[tokio::test]
+    async fn samples_metrics_only_while_running() {
+        let (manager, runner) = manager();
+        assert!(manager.sample_metrics().await.is_none());
+
+        manager.start().await.unwrap();
+        let pid = manager.get_status().await.pid.unwrap();
+        runner.set_stats(
+            pid,
+            ProcessStats {
+                cpu_time_ms: 100,
+                memory_bytes: 64 * 1024 * 1024,
+                threads: 12,
+                handles: 40,
+            },
+        );
+        runner.set_connections(Some(7));
+        let mut events = manager.subscribe_metrics();
+
+        let sample = manager.sample_metrics().await.unwrap();
+        assert_eq!(sample.pid, pid);
+        assert_eq!(sample.memory_bytes, 64 * 1024 * 1024);
+        assert_eq!(sample.threads, 12);
+        assert_eq!(sample.connections, Some(7));
+        assert_eq!(events.try_recv().unwrap().pid, pid);
+        assert_eq!(manager.get_metrics_history().len(), 1);
+
+        manager.stop().await.unwrap();
+        assert!(manager.sample_metrics().await.is_none());
+    }
+}
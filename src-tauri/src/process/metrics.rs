@@ -0,0 +1,118 @@
+use super::runner::ProcessStats;
+use super::KernelMetrics;
+use std::collections::VecDeque;
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+
+// 保存资源占用采样，并根据相邻两次采样计算CPU占用率
+pub struct MetricsRecorder {
+    history: VecDeque<KernelMetrics>,
+    capacity: usize,
+    // 上一次采样的 PID、累计CPU时间和采样时刻
+    last_cpu: Option<(u32, u64, Instant)>,
+}
+
+impl MetricsRecorder {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            history: VecDeque::new(),
+            capacity,
+            last_cpu: None,
+        }
+    }
+
+    // 记录一次采样，同一进程的第一次采样CPU占用率为0
+    pub fn record(
+        &mut self,
+        pid: u32,
+        stats: &ProcessStats,
+        connections: Option<u32>,
+        uptime_secs: u64,
+        now: Instant,
+    ) -> KernelMetrics {
+        let cpu_percent = match self.last_cpu {
+            Some((last_pid, last_cpu, last_time)) if last_pid == pid => {
+                let wall_ms = now.duration_since(last_time).as_millis() as f64;
+                let cpu_ms = stats.cpu_time_ms.saturating_sub(last_cpu) as f64;
+                let cores = std::thread::available_parallelism().map(|n| n.get()).unwrap_or(1) as f64;
+                if wall_ms > 0.0 {
+                    (cpu_ms / wall_ms / cores * 100.0).min(100.0)
+                } else {
+                    0.0
+                }
+            }
+            _ => 0.0,
+        };
+        self.last_cpu = Some((pid, stats.cpu_time_ms, now));
+
+        let sample = KernelMetrics {
+            pid,
+            cpu_percent,
+            memory_bytes: stats.memory_bytes,
+            threads: stats.threads,
+            handles: stats.handles,
+            connections,
+            uptime_secs,
+            timestamp: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_millis() as u64)
+                .unwrap_or_default(),
+        };
+
+        if self.history.len() >= self.capacity {
+            self.history.pop_front();
+        }
+        self.history.push_back(sample.clone());
+        sample
+    }
+
+    // 所有采样，最新的在后
+    pub fn history(&self) -> Vec<KernelMetrics> {
+        self.history.iter().cloned().collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn stats(cpu_time_ms: u64, memory_bytes: u64) -> ProcessStats {
+        ProcessStats {
+            cpu_time_ms,
+            memory_bytes,
+            threads: 8,
+            handles: 30,
+        }
+    }
+
+    #[test]
+    fn cpu_percent_from_consecutive_samples() {
+        let mut recorder = MetricsRecorder::new(10);
+        let start = Instant::now();
+        let cores = std::thread::available_parallelism().map(|n| n.get()).unwrap_or(1) as f64;
+
+        let first = recorder.record(1, &stats(1_000, 10), Some(3), 1, start);
+        assert_eq!(first.cpu_percent, 0.0);
+
+        let second = recorder.record(1, &stats(1_500, 20), Some(4), 2, start + Duration::from_secs(1));
+        assert!((second.cpu_percent - 50.0 / cores).abs() < 0.01);
+        assert_eq!(second.memory_bytes, 20);
+        assert_eq!(second.connections, Some(4));
+
+        // 进程变化后重新开始计算
+        let restarted = recorder.record(2, &stats(10, 5), None, 0, start + Duration::from_secs(2));
+        assert_eq!(restarted.cpu_percent, 0.0);
+    }
+
+    #[test]
+    fn history_is_bounded() {
+        let mut recorder = MetricsRecorder::new(2);
+        let now = Instant::now();
+        for memory in [1, 2, 3] {
+            recorder.record(1, &stats(0, memory), None, 0, now);
+        }
+
+        let memory: Vec<u64> = recorder.history().iter().map(|m| m.memory_bytes).collect();
+        assert_eq!(memory, vec![2, 3]);
+    }
+}
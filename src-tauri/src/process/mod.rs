@@ -3,6 +3,11 @@ use std::fmt;
 use crate::app::constants::process as process_constants;
 
 pub mod manager;
+pub mod metrics;
+pub mod output;
+pub mod pid_file;
+pub mod ports;
+pub mod runner;
 
 // 进程状态枚举
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
@@ -62,6 +67,57 @@ pub struct ProcessInfo {
     pub last_error: Option<String>,
 }
 
+// 内核崩溃记录
+#[derive(Debug, Clone, Serialize)]
+pub struct CrashRecord {
+    pub time: u64,              // 崩溃时间(Unix时间戳，秒)
+    pub exit_code: Option<i32>, // 退出码，无法获取时为空
+    pub reason: String,
+    pub output: Vec<String>,    // 崩溃前的内核输出
+    pub attempt: u32,           // 连续崩溃次数
+    pub will_restart: bool,     // 是否会自动重启
+}
+
+// 内核资源占用采样
+#[derive(Debug, Clone, Serialize)]
+pub struct KernelMetrics {
+    pub pid: u32,
+    pub cpu_percent: f64,          // 占全部CPU核心的百分比
+    pub memory_bytes: u64,         // 物理内存占用
+    pub threads: u32,
+    pub handles: u32,              // 句柄数，Unix下为文件描述符数
+    pub connections: Option<u32>,  // Clash API 中的连接数
+    pub uptime_secs: u64,          // 运行时长(秒)
+    pub timestamp: u64,            // 采样时间(Unix时间戳，毫秒)
+}
+
+// 进程状态变化事件
+#[derive(Debug, Clone, Serialize)]
+pub struct StatusEvent {
+    pub status: ProcessStatus,
+    pub pid: Option<u32>,
+    pub reason: Option<String>, // 失败原因
+    pub timestamp: u64,         // 变化时间(Unix时间戳，毫秒)
+}
+
+impl StatusEvent {
+    pub fn from_info(info: &ProcessInfo) -> Self {
+        let reason = match &info.status {
+            ProcessStatus::Failed(reason) => Some(reason.clone()),
+            _ => None,
+        };
+        Self {
+            status: info.status.clone(),
+            pid: info.pid,
+            reason,
+            timestamp: std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .map(|d| d.as_millis() as u64)
+                .unwrap_or_default(),
+        }
+    }
+}
+
 pub type Result<T> = std::result::Result<T, ProcessError>;
 
 // 进程配置结构体
@@ -71,6 +127,7 @@ pub struct ProcessConfig {
     pub health_check_interval: u64, // 健康检查间隔(秒)
     pub max_restart_attempts: u32,  // 最大重启尝试次数
     pub restart_delay: u64,         // 重启延迟时间(秒)
+    pub ready_timeout: u64,         // 等待内核就绪的超时时间(秒)
 }
 
 impl Default for ProcessConfig {
@@ -80,6 +137,7 @@ impl Default for ProcessConfig {
             health_check_interval: process_constants::HEALTH_CHECK_INTERVAL,
             max_restart_attempts: process_constants::MAX_RESTART_ATTEMPTS,
             restart_delay: process_constants::RESTART_DELAY,
+            ready_timeout: process_constants::READY_TIMEOUT,
         }
     }
 }
@@ -0,0 +1,188 @@
+use crate::app::constants::process;
+use std::collections::VecDeque;
+use std::fs::{self, File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use tracing::warn;
+
+// 内核输出：内存中保留最近的若干行，同时写入按大小轮转的日志文件
+pub struct KernelOutput {
+    lines: VecDeque<String>,
+    capacity: usize,
+    // 累计写入的行数，用于区分每次启动产生的输出
+    total: u64,
+    log_path: PathBuf,
+    max_size: u64,
+    max_files: usize,
+    file: Option<File>,
+    file_size: u64,
+}
+
+impl KernelOutput {
+    pub fn new(log_path: PathBuf) -> Self {
+        Self::with_limits(
+            log_path,
+            process::OUTPUT_BUFFER_LINES,
+            process::KERNEL_LOG_MAX_SIZE,
+            process::KERNEL_LOG_MAX_FILES,
+        )
+    }
+
+    pub fn with_limits(log_path: PathBuf, capacity: usize, max_size: u64, max_files: usize) -> Self {
+        Self {
+            lines: VecDeque::with_capacity(capacity.min(1024)),
+            capacity,
+            total: 0,
+            log_path,
+            max_size,
+            max_files,
+            file: None,
+            file_size: 0,
+        }
+    }
+
+    // 当前位置，配合 lines_since 获取之后产生的输出
+    pub fn mark(&self) -> u64 {
+        self.total
+    }
+
+    // 记录一行输出
+    pub fn push(&mut self, line: String) {
+        self.write_file(&line);
+
+        if self.lines.len() >= self.capacity {
+            self.lines.pop_front();
+        }
+        self.lines.push_back(line);
+        self.total += 1;
+    }
+
+    // 最近的 count 行
+    pub fn tail(&self, count: usize) -> Vec<String> {
+        let skip = self.lines.len().saturating_sub(count);
+        self.lines.iter().skip(skip).cloned().collect()
+    }
+
+    // mark 之后产生的最近 count 行
+    pub fn lines_since(&self, mark: u64, count: usize) -> Vec<String> {
+        let new_lines = self.total.saturating_sub(mark).min(self.lines.len() as u64) as usize;
+        self.tail(new_lines.min(count))
+    }
+
+    // 写入日志文件，失败时只记录警告，不影响内存中的输出
+    fn write_file(&mut self, line: &str) {
+        if self.file.is_some() && self.file_size >= self.max_size {
+            self.file = None;
+            rotate_logs(&self.log_path, self.max_files);
+        }
+
+        if self.file.is_none() {
+            match open_log(&self.log_path) {
+                Ok((file, size)) => {
+                    self.file = Some(file);
+                    self.file_size = size;
+                }
+                Err(e) => {
+                    warn!("打开内核日志文件失败: {}", e);
+                    return;
+                }
+            }
+        }
+
+        if let Some(file) = self.file.as_mut() {
+            match writeln!(file, "{}", line) {
+                Ok(_) => self.file_size += line.len() as u64 + 1,
+                Err(e) => {
+                    warn!("写入内核日志文件失败: {}", e);
+                    self.file = None;
+                }
+            }
+        }
+    }
+}
+
+fn open_log(path: &Path) -> std::io::Result<(File, u64)> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let file = OpenOptions::new().create(true).append(true).open(path)?;
+    let size = file.metadata().map(|m| m.len()).unwrap_or(0);
+    Ok((file, size))
+}
+
+// 第 index 个历史日志文件，例如 kernel.log.1
+fn rotated_path(path: &Path, index: usize) -> PathBuf {
+    let mut name = path.as_os_str().to_os_string();
+    name.push(format!(".{}", index));
+    PathBuf::from(name)
+}
+
+// 轮转日志文件：kernel.log -> kernel.log.1 -> kernel.log.2 ...，超出数量的删除
+fn rotate_logs(path: &Path, max_files: usize) {
+    if max_files == 0 {
+        let _ = fs::remove_file(path);
+        return;
+    }
+
+    let _ = fs::remove_file(rotated_path(path, max_files));
+    for index in (1..max_files).rev() {
+        let from = rotated_path(path, index);
+        if from.exists() {
+            let _ = fs::rename(&from, rotated_path(path, index + 1));
+        }
+    }
+    if let Err(e) = fs::rename(path, rotated_path(path, 1)) {
+        warn!("轮转内核日志文件失败: {}", e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_log(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("kernel-output-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        dir.join("kernel.log")
+    }
+
+    #[test]
+    fn keeps_only_recent_lines() {
+        let mut output = KernelOutput::with_limits(temp_log("ring"), 3, 1024, 1);
+        for i in 0..5 {
+            output.push(format!("line {}", i));
+        }
+
+        assert_eq!(output.tail(10), vec!["line 2", "line 3", "line 4"]);
+        assert_eq!(output.tail(1), vec!["line 4"]);
+    }
+
+    #[test]
+    fn lines_since_mark() {
+        let mut output = KernelOutput::with_limits(temp_log("mark"), 10, 1024, 1);
+        output.push("old".to_string());
+        let mark = output.mark();
+        output.push("new 1".to_string());
+        output.push("new 2".to_string());
+
+        assert_eq!(output.lines_since(mark, 10), vec!["new 1", "new 2"]);
+        assert_eq!(output.lines_since(mark, 1), vec!["new 2"]);
+        assert!(output.lines_since(output.mark(), 10).is_empty());
+    }
+
+    #[test]
+    fn rotates_log_files() {
+        let path = temp_log("rotate");
+        let mut output = KernelOutput::with_limits(path.clone(), 10, 10, 2);
+        for i in 0..4 {
+            output.push(format!("line-{:04}", i));
+        }
+
+        assert_eq!(fs::read_to_string(&path).unwrap(), "line-0003\n");
+        assert_eq!(fs::read_to_string(rotated_path(&path, 1)).unwrap(), "line-0002\n");
+        assert_eq!(fs::read_to_string(rotated_path(&path, 2)).unwrap(), "line-0001\n");
+        assert!(!rotated_path(&path, 3).exists());
+
+        let _ = fs::remove_dir_all(path.parent().unwrap());
+    }
+}
@@ -0,0 +1,46 @@
+use super::runner::ProcessIdentity;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use tracing::warn;
+
+// 记录由本应用启动的内核进程，应用异常退出后据此找回遗留的内核
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct KernelPidFile {
+    pub pid: u32,
+    #[serde(flatten)]
+    pub identity: ProcessIdentity,
+}
+
+// 读取PID文件，不存在或格式错误时返回空
+pub fn read(path: &Path) -> Option<KernelPidFile> {
+    let content = std::fs::read_to_string(path).ok()?;
+    match serde_json::from_str(&content) {
+        Ok(record) => Some(record),
+        Err(e) => {
+            warn!("PID文件格式错误，已忽略: {}", e);
+            None
+        }
+    }
+}
+
+// 写入PID文件
+pub fn write(path: &Path, record: &KernelPidFile) {
+    if let Some(parent) = path.parent() {
+        let _ = std::fs::create_dir_all(parent);
+    }
+    let result = serde_json::to_string_pretty(record)
+        .map_err(|e| e.to_string())
+        .and_then(|content| std::fs::write(path, content).map_err(|e| e.to_string()));
+    if let Err(e) = result {
+        warn!("写入PID文件失败: {}", e);
+    }
+}
+
+// 删除PID文件
+pub fn remove(path: &Path) {
+    if path.exists() {
+        if let Err(e) = std::fs::remove_file(path) {
+            warn!("删除PID文件失败: {}", e);
+        }
+    }
+}
@@ -0,0 +1,91 @@
+use crate::app::constants::network;
+use crate::entity::config_model::SingBoxConfig;
+use serde::Serialize;
+use std::fmt;
+
+// 端口用途
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub enum PortUsage {
+    // 混合入站，即系统代理使用的端口
+    MixedInbound,
+    // 其他入站，值为入站类型
+    Inbound(String),
+    ClashApi,
+}
+
+// 配置中需要监听的端口
+#[derive(Debug, Clone, Serialize)]
+pub struct ListenPort {
+    pub usage: PortUsage,
+    // 入站标签，Clash API 为固定名称
+    pub name: String,
+    pub address: String,
+    pub port: u16,
+}
+
+// 被占用的端口及占用者
+#[derive(Debug, Clone, Serialize)]
+pub struct PortConflict {
+    pub listen: ListenPort,
+    pub owner_pid: Option<u32>,
+    pub owner_name: Option<String>,
+}
+
+impl fmt::Display for PortConflict {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "端口 {} ({}) 已被占用", self.listen.port, self.listen.name)?;
+        match (&self.owner_name, self.owner_pid) {
+            (Some(name), Some(pid)) => write!(f, "，占用进程: {} (PID: {})", name, pid),
+            (None, Some(pid)) => write!(f, "，占用进程 PID: {}", pid),
+            _ => Ok(()),
+        }
+    }
+}
+
+// 获取配置中所有需要监听的TCP端口
+pub fn listen_ports(config: &SingBoxConfig) -> Vec<ListenPort> {
+    let mut ports: Vec<ListenPort> = config
+        .inbounds
+        .iter()
+        .filter_map(|inbound| {
+            let port = inbound.listen_port.filter(|p| *p != 0)?;
+            let usage = if inbound.r#type == "mixed" {
+                PortUsage::MixedInbound
+            } else {
+                PortUsage::Inbound(inbound.r#type.clone())
+            };
+            Some(ListenPort {
+                usage,
+                name: inbound.tag.clone().unwrap_or_else(|| inbound.r#type.clone()),
+                address: probe_address(inbound.listen.as_deref()),
+                port,
+            })
+        })
+        .collect();
+
+    let controller = config
+        .experimental
+        .as_ref()
+        .and_then(|e| e.clash_api.as_ref())
+        .and_then(|c| c.external_controller.as_deref());
+    if let Some((host, port)) = controller.and_then(|c| c.rsplit_once(':')) {
+        if let Ok(port) = port.parse::<u16>() {
+            ports.push(ListenPort {
+                usage: PortUsage::ClashApi,
+                name: "Clash API".to_string(),
+                address: probe_address(Some(host.trim_matches(|c| c == '[' || c == ']'))),
+                port,
+            });
+        }
+    }
+
+    ports
+}
+
+// 检查端口时使用的地址，未指定时按本机地址检查
+fn probe_address(listen: Option<&str>) -> String {
+    match listen {
+        Some(address) if !address.is_empty() => address.to_string(),
+        _ => network::LOCAL_LISTEN_ADDRESS.to_string(),
+    }
+}
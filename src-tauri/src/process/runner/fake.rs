@@ -0,0 +1,234 @@
+use super::{KernelChild, KernelEndpoints, KernelRunner, ProcessIdentity, ProcessStats};
+use futures::future::BoxFuture;
+use std::collections::{BTreeMap, HashMap};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+// 内存中的模拟实现，用于测试进程生命周期
+pub struct FakeRunner {
+    state: Mutex<FakeState>,
+}
+
+struct FakeState {
+    next_pid: u32,
+    processes: BTreeMap<u32, FakeProcess>,
+    spawn_error: Option<String>,
+    config_error: Option<String>,
+    // 为 true 时忽略正常退出请求，只能强制结束
+    ignore_terminate: bool,
+    // 被占用的端口 -> 占用端口的进程
+    busy_ports: BTreeMap<u16, Option<u32>>,
+    // PID -> 资源占用
+    stats: BTreeMap<u32, ProcessStats>,
+    connections: Option<u32>,
+    // 就绪检查的结果
+    ready: bool,
+    // 为 true 时启动的进程立即退出
+    exit_on_spawn: bool,
+    calls: Vec<String>,
+}
+
+struct FakeProcess {
+    exe_path: PathBuf,
+    start_time: u64,
+}
+
+impl FakeProcess {
+    fn name(&self) -> &str {
+        self.exe_path.file_name().and_then(|n| n.to_str()).unwrap_or_default()
+    }
+}
+
+impl FakeState {
+    fn insert(&mut self, exe_path: PathBuf) -> u32 {
+        let pid = self.next_pid;
+        self.next_pid += 1;
+        self.processes.insert(
+            pid,
+            FakeProcess {
+                exe_path,
+                start_time: pid as u64 * 10,
+            },
+        );
+        pid
+    }
+}
+
+impl FakeRunner {
+    pub fn new() -> Self {
+        Self {
+            state: Mutex::new(FakeState {
+                next_pid: 1000,
+                processes: BTreeMap::new(),
+                spawn_error: None,
+                config_error: None,
+                ignore_terminate: false,
+                busy_ports: BTreeMap::new(),
+                stats: BTreeMap::new(),
+                connections: None,
+                ready: true,
+                exit_on_spawn: false,
+                calls: Vec::new(),
+            }),
+        }
+    }
+
+    // 模拟一个不由管理器启动的进程
+    pub fn add_process(&self, exe_path: &Path) -> u32 {
+        self.state.lock().unwrap().insert(exe_path.to_path_buf())
+    }
+
+    // 模拟进程意外退出
+    pub fn exit_process(&self, pid: u32) {
+        self.state.lock().unwrap().processes.remove(&pid);
+    }
+
+    pub fn set_spawn_error(&self, error: Option<&str>) {
+        self.state.lock().unwrap().spawn_error = error.map(|e| e.to_string());
+    }
+
+    pub fn set_config_error(&self, error: Option<&str>) {
+        self.state.lock().unwrap().config_error = error.map(|e| e.to_string());
+    }
+
+    pub fn set_ignore_terminate(&self, ignore: bool) {
+        self.state.lock().unwrap().ignore_terminate = ignore;
+    }
+
+    // 模拟端口被占用
+    pub fn set_port_busy(&self, port: u16, owner: Option<u32>) {
+        self.state.lock().unwrap().busy_ports.insert(port, owner);
+    }
+
+    pub fn set_stats(&self, pid: u32, stats: ProcessStats) {
+        self.state.lock().unwrap().stats.insert(pid, stats);
+    }
+
+    pub fn set_connections(&self, connections: Option<u32>) {
+        self.state.lock().unwrap().connections = connections;
+    }
+
+    pub fn set_ready(&self, ready: bool) {
+        self.state.lock().unwrap().ready = ready;
+    }
+
+    pub fn set_exit_on_spawn(&self, exit: bool) {
+        self.state.lock().unwrap().exit_on_spawn = exit;
+    }
+
+    pub fn running_pids(&self) -> Vec<u32> {
+        self.state.lock().unwrap().processes.keys().copied().collect()
+    }
+
+    // 按调用顺序记录的操作，例如 "spawn"、"kill 1000"
+    pub fn calls(&self) -> Vec<String> {
+        self.state.lock().unwrap().calls.clone()
+    }
+}
+
+impl Default for FakeRunner {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl KernelRunner for FakeRunner {
+    fn spawn(&self, kernel_path: &Path, _work_dir: &Path) -> io::Result<KernelChild> {
+        let mut state = self.state.lock().unwrap();
+        state.calls.push("spawn".to_string());
+        if let Some(error) = state.spawn_error.clone() {
+            return Err(io::Error::other(error));
+        }
+
+        let pid = state.insert(kernel_path.to_path_buf());
+        if state.exit_on_spawn {
+            state.processes.remove(&pid);
+        }
+
+        Ok(KernelChild { pid, child: None })
+    }
+
+    fn process_exists(&self, pid: u32) -> bool {
+        self.state.lock().unwrap().processes.contains_key(&pid)
+    }
+
+    fn find_by_name(&self, name: &str) -> Vec<u32> {
+        let state = self.state.lock().unwrap();
+        state
+            .processes
+            .iter()
+            .filter(|(_, p)| p.name() == name)
+            .map(|(pid, _)| *pid)
+            .collect()
+    }
+
+    fn process_identity(&self, pid: u32) -> Option<ProcessIdentity> {
+        let state = self.state.lock().unwrap();
+        state.processes.get(&pid).map(|p| ProcessIdentity {
+            exe_path: Some(p.exe_path.clone()),
+            start_time: Some(p.start_time),
+        })
+    }
+
+    fn process_stats(&self, pid: u32) -> Option<ProcessStats> {
+        let state = self.state.lock().unwrap();
+        if !state.processes.contains_key(&pid) {
+            return None;
+        }
+        Some(state.stats.get(&pid).cloned().unwrap_or_default())
+    }
+
+    fn connection_count<'a>(&'a self, _endpoints: &'a KernelEndpoints) -> BoxFuture<'a, Option<u32>> {
+        let connections = self.state.lock().unwrap().connections;
+        Box::pin(async move { connections })
+    }
+
+    fn port_owners(&self, ports: &[u16]) -> HashMap<u16, u32> {
+        let mut state = self.state.lock().unwrap();
+        state.calls.push("port_owners".to_string());
+        ports
+            .iter()
+            .filter_map(|port| Some((*port, state.busy_ports.get(port).copied().flatten()?)))
+            .collect()
+    }
+
+    fn is_port_free(&self, _address: &str, port: u16) -> bool {
+        !self.state.lock().unwrap().busy_ports.contains_key(&port)
+    }
+
+    fn terminate(&self, pid: u32) -> io::Result<()> {
+        let mut state = self.state.lock().unwrap();
+        state.calls.push(format!("terminate {}", pid));
+        if state.ignore_terminate {
+            return Ok(());
+        }
+        match state.processes.remove(&pid) {
+            Some(_) => Ok(()),
+            None => Err(io::Error::new(io::ErrorKind::NotFound, "进程不存在")),
+        }
+    }
+
+    fn kill(&self, pid: u32) -> io::Result<()> {
+        let mut state = self.state.lock().unwrap();
+        state.calls.push(format!("kill {}", pid));
+        match state.processes.remove(&pid) {
+            Some(_) => Ok(()),
+            None => Err(io::Error::new(io::ErrorKind::NotFound, "进程不存在")),
+        }
+    }
+
+    fn check_config(&self, _config_path: &Path) -> Result<(), String> {
+        let mut state = self.state.lock().unwrap();
+        state.calls.push("check_config".to_string());
+        match state.config_error.clone() {
+            Some(error) => Err(error),
+            None => Ok(()),
+        }
+    }
+
+    fn is_ready<'a>(&'a self, _endpoints: &'a KernelEndpoints) -> BoxFuture<'a, bool> {
+        let ready = self.state.lock().unwrap().ready;
+        Box::pin(async move { ready })
+    }
+}
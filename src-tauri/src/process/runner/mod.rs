@@ -0,0 +1,265 @@
+//! 内核进程的系统操作
+//!
+//! ProcessManager 只负责生命周期逻辑，启动、查询和结束进程等系统相关操作
+//! 由 KernelRunner 实现，便于在不同平台和测试中替换
+
+use crate::app::constants::{messages, network};
+use crate::utils::config_util;
+use futures::future::BoxFuture;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::io;
+use std::net::{SocketAddr, TcpListener};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+#[cfg(test)]
+pub mod fake;
+#[cfg(unix)]
+pub mod unix;
+#[cfg(windows)]
+pub mod windows;
+
+// 已启动的内核进程
+pub struct KernelChild {
+    pub pid: u32,
+    // 真实进程的句柄，模拟实现中为空
+    pub child: Option<tokio::process::Child>,
+}
+
+// 用于确认PID对应的仍是同一个进程，避免PID被复用后误操作其他进程
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ProcessIdentity {
+    // 可执行文件路径
+    pub exe_path: Option<PathBuf>,
+    // 进程启动时间，只用于比较，单位由系统决定
+    pub start_time: Option<u64>,
+}
+
+impl ProcessIdentity {
+    // 是否与记录的进程相同，至少需要一项可比较且全部一致
+    pub fn matches(&self, recorded: &ProcessIdentity) -> bool {
+        let mut compared = false;
+        if let (Some(current), Some(recorded)) = (self.start_time, recorded.start_time) {
+            if current != recorded {
+                return false;
+            }
+            compared = true;
+        }
+        if let (Some(current), Some(recorded)) = (&self.exe_path, &recorded.exe_path) {
+            if !same_path(current, recorded) {
+                return false;
+            }
+            compared = true;
+        }
+        compared
+    }
+
+    // 是否由指定的可执行文件启动
+    pub fn is_executable(&self, path: &Path) -> bool {
+        self.exe_path.as_deref().map(|exe| same_path(exe, path)).unwrap_or(false)
+    }
+}
+
+// 比较两个路径，尽量解析为绝对路径后再比较
+fn same_path(a: &Path, b: &Path) -> bool {
+    let a = std::fs::canonicalize(a).unwrap_or_else(|_| a.to_path_buf());
+    let b = std::fs::canonicalize(b).unwrap_or_else(|_| b.to_path_buf());
+    if cfg!(windows) {
+        a.to_string_lossy().eq_ignore_ascii_case(&b.to_string_lossy())
+    } else {
+        a == b
+    }
+}
+
+// 进程资源占用
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ProcessStats {
+    // 累计CPU时间(毫秒)
+    pub cpu_time_ms: u64,
+    // 物理内存占用(字节)
+    pub memory_bytes: u64,
+    pub threads: u32,
+    // 句柄数，Unix下为打开的文件描述符数
+    pub handles: u32,
+}
+
+// 内核对外提供服务的地址，用于就绪检查和读取运行状态
+#[derive(Debug, Clone, Default)]
+pub struct KernelEndpoints {
+    // 混合入站端口
+    pub mixed_port: Option<u16>,
+    // Clash API 地址(host:port)
+    pub clash_api: Option<String>,
+    pub clash_secret: Option<String>,
+}
+
+impl KernelEndpoints {
+    // 从配置文件读取需要检查的地址
+    pub fn from_config(config_path: &Path) -> Self {
+        let config = match config_util::load_config(config_path) {
+            Ok(config) => config,
+            Err(_) => return Self::default(),
+        };
+
+        let mixed_port = config
+            .inbounds
+            .iter()
+            .find(|i| i.r#type == "mixed")
+            .and_then(|i| i.listen_port);
+        let clash_api = config.experimental.as_ref().and_then(|e| e.clash_api.as_ref());
+
+        Self {
+            mixed_port,
+            clash_api: clash_api.and_then(|c| c.external_controller.clone()),
+            clash_secret: clash_api.and_then(|c| c.secret.clone()).filter(|s| !s.is_empty()),
+        }
+    }
+}
+
+pub trait KernelRunner: Send + Sync {
+    // 启动内核
+    fn spawn(&self, kernel_path: &Path, work_dir: &Path) -> io::Result<KernelChild>;
+
+    // 检查进程是否存在
+    fn process_exists(&self, pid: u32) -> bool;
+
+    // 阻塞等待进程退出，超时返回 false，默认轮询进程是否存在
+    fn wait_for_exit(&self, pid: u32, timeout: Duration) -> bool {
+        poll_until_exited(self, pid, timeout)
+    }
+
+    // 按进程名称查找进程，返回所有匹配的PID
+    fn find_by_name(&self, name: &str) -> Vec<u32>;
+
+    // 获取进程的可执行文件路径和启动时间，进程不存在时返回空
+    fn process_identity(&self, pid: u32) -> Option<ProcessIdentity>;
+
+    // 查找监听指定TCP端口的进程，只读取一次系统的端口表
+    // 返回端口 -> PID，系统不支持或无权限时缺少对应的端口
+    fn port_owners(&self, ports: &[u16]) -> HashMap<u16, u32>;
+
+    // 获取进程的资源占用，进程不存在时返回空
+    fn process_stats(&self, pid: u32) -> Option<ProcessStats>;
+
+    // 通过 Clash API 获取当前连接数
+    fn connection_count<'a>(&'a self, endpoints: &'a KernelEndpoints) -> BoxFuture<'a, Option<u32>> {
+        Box::pin(async move {
+            let controller = endpoints.clash_api.as_deref()?;
+            let body = clash_api_get(controller, endpoints.clash_secret.as_deref(), "/connections")
+                .await
+                .ok()?;
+            let value: serde_json::Value = serde_json::from_str(&body).ok()?;
+            value
+                .get("connections")
+                .and_then(|c| c.as_array())
+                .map(|c| c.len() as u32)
+        })
+    }
+
+    // 检查端口是否可以监听
+    fn is_port_free(&self, address: &str, port: u16) -> bool {
+        TcpListener::bind((address, port)).is_ok()
+    }
+
+    // 请求进程正常退出
+    fn terminate(&self, pid: u32) -> io::Result<()>;
+
+    // 强制结束进程
+    fn kill(&self, pid: u32) -> io::Result<()>;
+
+    // 检查配置文件，默认先验证JSON格式再使用内核自带的检查
+    fn check_config(&self, config_path: &Path) -> Result<(), String> {
+        if !config_path.exists() {
+            return Err(messages::ERR_CONFIG_READ_FAILED.to_string());
+        }
+
+        let config_str = std::fs::read_to_string(config_path)
+            .map_err(|e| format!("{}: {}", messages::ERR_CONFIG_READ_FAILED, e))?;
+        if let Err(e) = serde_json::from_str::<serde_json::Value>(&config_str) {
+            return Err(format!("配置文件JSON格式错误: {}", e));
+        }
+
+        config_util::verify_config(config_path)
+    }
+
+    // 检查内核是否已经开始提供服务：混合端口可连接且 Clash API /version 正常响应
+    fn is_ready<'a>(&'a self, endpoints: &'a KernelEndpoints) -> BoxFuture<'a, bool> {
+        Box::pin(async move {
+            if let Some(port) = endpoints.mixed_port {
+                let addr = SocketAddr::from(([127, 0, 0, 1], port));
+                let connect = tokio::time::timeout(Duration::from_millis(500), tokio::net::TcpStream::connect(addr));
+                if !matches!(connect.await, Ok(Ok(_))) {
+                    return false;
+                }
+            }
+
+            match endpoints.clash_api.as_deref() {
+                Some(controller) => clash_api_get(controller, endpoints.clash_secret.as_deref(), "/version")
+                    .await
+                    .is_ok(),
+                None => true,
+            }
+        })
+    }
+}
+
+// 轮询直到进程退出，超时返回 false
+pub fn poll_until_exited<R: KernelRunner + ?Sized>(runner: &R, pid: u32, timeout: Duration) -> bool {
+    let start = Instant::now();
+    while runner.process_exists(pid) {
+        if start.elapsed() > timeout {
+            return false;
+        }
+        std::thread::sleep(Duration::from_millis(100));
+    }
+    true
+}
+
+// 请求 Clash API，返回响应内容
+async fn clash_api_get(controller: &str, secret: Option<&str>, path: &str) -> Result<String, String> {
+    // 监听所有地址时通过本机地址访问
+    let (host, port) = controller.rsplit_once(':').unwrap_or((controller, ""));
+    let host = match host.trim_matches(|c| c == '[' || c == ']') {
+        "" | "0.0.0.0" | "::" => network::DEFAULT_CLASH_API_ADDRESS.to_string(),
+        host if host.contains(':') => format!("[{}]", host),
+        host => host.to_string(),
+    };
+    let port: u16 = port.parse().map_err(|_| "无效的 Clash API 地址".to_string())?;
+
+    // 创建禁用代理的HTTP客户端，系统代理指向内核本身
+    let client = reqwest::Client::builder()
+        .no_proxy()
+        .timeout(Duration::from_secs(2))
+        .build()
+        .map_err(|e| format!("创建HTTP客户端失败: {}", e))?;
+
+    let mut request = client.get(format!("http://{}:{}{}", host, port, path));
+    if let Some(secret) = secret {
+        request = request.bearer_auth(secret);
+    }
+    let response = request
+        .send()
+        .await
+        .map_err(|e| format!("请求 Clash API 失败: {}", e))?;
+    if !response.status().is_success() {
+        return Err(format!("Clash API 响应异常: {}", response.status()));
+    }
+    response
+        .text()
+        .await
+        .map_err(|e| format!("读取 Clash API 响应失败: {}", e))
+}
+
+// 当前平台的默认实现
+#[cfg(windows)]
+pub fn default_runner() -> Arc<dyn KernelRunner> {
+    Arc::new(windows::WindowsRunner)
+}
+
+// 当前平台的默认实现
+#[cfg(unix)]
+pub fn default_runner() -> Arc<dyn KernelRunner> {
+    Arc::new(unix::UnixRunner)
+}
@@ -0,0 +1,188 @@
+use super::{KernelChild, KernelRunner, ProcessIdentity, ProcessStats};
+use std::collections::HashMap;
+use std::io;
+use std::path::Path;
+use std::process::Stdio;
+use tokio::process::Command;
+
+// 基于信号和 /proc 的 Unix 实现
+pub struct UnixRunner;
+
+impl UnixRunner {
+    fn send_signal(pid: u32, signal: libc::c_int) -> io::Result<()> {
+        if unsafe { libc::kill(pid as libc::pid_t, signal) } == 0 {
+            Ok(())
+        } else {
+            Err(io::Error::last_os_error())
+        }
+    }
+
+    // 回收已退出的子进程，避免僵尸进程被误认为仍在运行
+    fn reap(pid: u32) {
+        let mut status = 0;
+        unsafe {
+            libc::waitpid(pid as libc::pid_t, &mut status, libc::WNOHANG);
+        }
+    }
+}
+
+impl KernelRunner for UnixRunner {
+    fn spawn(&self, kernel_path: &Path, work_dir: &Path) -> io::Result<KernelChild> {
+        let child = Command::new(kernel_path)
+            .arg("run")
+            .arg("-D")
+            .arg(work_dir)
+            // 输出由管理器读取，用于就绪检查失败时的错误信息和日志
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()?;
+
+        Ok(KernelChild {
+            pid: child.id().unwrap_or(0),
+            child: Some(child),
+        })
+    }
+
+    fn process_exists(&self, pid: u32) -> bool {
+        match std::fs::read_to_string(format!("/proc/{}/stat", pid)) {
+            Ok(stat) => {
+                // 进程状态位于进程名括号之后
+                let state = stat
+                    .rsplit_once(')')
+                    .and_then(|(_, rest)| rest.trim_start().chars().next());
+                if state == Some('Z') {
+                    Self::reap(pid);
+                    return false;
+                }
+                true
+            }
+            Err(_) if Path::new("/proc/self").exists() => false,
+            // 没有 /proc 的系统使用空信号检查
+            Err(_) => Self::send_signal(pid, 0).is_ok(),
+        }
+    }
+
+    fn find_by_name(&self, name: &str) -> Vec<u32> {
+        let entries = match std::fs::read_dir("/proc") {
+            Ok(entries) => entries,
+            Err(_) => return Vec::new(),
+        };
+
+        // /proc/<pid>/comm 中的名称最多保留15个字符
+        let expected: String = name.chars().take(15).collect();
+        let self_pid = std::process::id();
+
+        entries
+            .filter_map(|entry| entry.ok())
+            .filter_map(|entry| entry.file_name().to_str()?.parse::<u32>().ok())
+            .filter(|pid| *pid != self_pid)
+            .filter(|pid| {
+                std::fs::read_to_string(format!("/proc/{}/comm", pid))
+                    .map(|comm| comm.trim_end() == expected)
+                    .unwrap_or(false)
+            })
+            .filter(|pid| self.process_exists(*pid))
+            .collect()
+    }
+
+    fn process_identity(&self, pid: u32) -> Option<ProcessIdentity> {
+        let stat = std::fs::read_to_string(format!("/proc/{}/stat", pid)).ok()?;
+        // 进程名之后的第20个字段为启动时间（系统启动后的时钟周期数）
+        let start_time = stat
+            .rsplit_once(')')
+            .and_then(|(_, rest)| rest.split_whitespace().nth(19))
+            .and_then(|value| value.parse::<u64>().ok());
+
+        Some(ProcessIdentity {
+            exe_path: std::fs::read_link(format!("/proc/{}/exe", pid)).ok(),
+            start_time,
+        })
+    }
+
+    fn process_stats(&self, pid: u32) -> Option<ProcessStats> {
+        let stat = std::fs::read_to_string(format!("/proc/{}/stat", pid)).ok()?;
+        let fields: Vec<&str> = stat.rsplit_once(')')?.1.split_whitespace().collect();
+        // 进程名之后依次为第3个字段开始的内容：utime 为第14个，stime 为第15个，线程数为第20个
+        let field = |n: usize| fields.get(n - 3).and_then(|v| v.parse::<u64>().ok());
+        let ticks = field(14)? + field(15)?;
+        let threads = field(20)? as u32;
+
+        // statm 的第二个字段为常驻内存页数
+        let resident_pages = std::fs::read_to_string(format!("/proc/{}/statm", pid))
+            .ok()
+            .and_then(|statm| statm.split_whitespace().nth(1)?.parse::<u64>().ok())
+            .unwrap_or(0);
+        let handles = std::fs::read_dir(format!("/proc/{}/fd", pid))
+            .map(|fds| fds.count() as u32)
+            .unwrap_or(0);
+
+        let (clock_ticks, page_size) = unsafe {
+            (libc::sysconf(libc::_SC_CLK_TCK), libc::sysconf(libc::_SC_PAGESIZE))
+        };
+        Some(ProcessStats {
+            cpu_time_ms: ticks * 1000 / clock_ticks.max(1) as u64,
+            memory_bytes: resident_pages * page_size.max(0) as u64,
+            threads,
+            handles,
+        })
+    }
+
+    fn port_owners(&self, ports: &[u16]) -> HashMap<u16, u32> {
+        // 从 /proc/net/tcp 中找到监听这些端口的 socket inode
+        let sockets: HashMap<String, u16> = ["/proc/net/tcp", "/proc/net/tcp6"]
+            .iter()
+            .filter_map(|path| std::fs::read_to_string(path).ok())
+            .flat_map(|content| {
+                content
+                    .lines()
+                    .skip(1)
+                    .filter_map(|line| {
+                        // 字段依次为 序号、本地地址、远程地址、状态...，第10个字段为 inode
+                        let fields: Vec<&str> = line.split_whitespace().collect();
+                        let local_port = u16::from_str_radix(fields.get(1)?.rsplit_once(':')?.1, 16).ok()?;
+                        let listening = *fields.get(3)? == "0A";
+                        if !listening || !ports.contains(&local_port) {
+                            return None;
+                        }
+                        Some((format!("socket:[{}]", fields.get(9)?), local_port))
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .collect();
+        let mut owners = HashMap::new();
+        if sockets.is_empty() {
+            return owners;
+        }
+
+        // 再查找持有这些 socket 的进程，没有权限读取的进程会被跳过
+        let pids = match std::fs::read_dir("/proc") {
+            Ok(entries) => entries
+                .filter_map(|entry| entry.ok())
+                .filter_map(|entry| entry.file_name().to_str()?.parse::<u32>().ok()),
+            Err(_) => return owners,
+        };
+        for pid in pids {
+            let fds = match std::fs::read_dir(format!("/proc/{}/fd", pid)) {
+                Ok(fds) => fds,
+                Err(_) => continue,
+            };
+            for target in fds.filter_map(|fd| fd.ok()).filter_map(|fd| std::fs::read_link(fd.path()).ok()) {
+                if let Some(port) = target.to_str().and_then(|target| sockets.get(target)) {
+                    owners.entry(*port).or_insert(pid);
+                }
+            }
+            if owners.len() == ports.len() {
+                break;
+            }
+        }
+        owners
+    }
+
+    fn terminate(&self, pid: u32) -> io::Result<()> {
+        Self::send_signal(pid, libc::SIGTERM)
+    }
+
+    fn kill(&self, pid: u32) -> io::Result<()> {
+        Self::send_signal(pid, libc::SIGKILL)
+    }
+}
@@ -0,0 +1,325 @@
+use super::{KernelChild, KernelRunner, ProcessIdentity, ProcessStats};
+use crate::app::constants::process;
+use std::collections::HashMap;
+use std::io;
+use std::os::windows::process::CommandExt;
+use std::os::windows::ffi::OsStringExt;
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::sync::Mutex;
+use std::time::Duration;
+use tokio::process::Command;
+use tracing::error;
+use winapi::shared::minwindef::FILETIME;
+use winapi::shared::winerror::{ERROR_ACCESS_DENIED, WAIT_TIMEOUT};
+use winapi::um::consoleapi::SetConsoleCtrlHandler;
+use winapi::um::handleapi::{CloseHandle, INVALID_HANDLE_VALUE};
+use winapi::um::processthreadsapi::{GetProcessHandleCount, GetProcessTimes, OpenProcess};
+use winapi::um::synchapi::WaitForSingleObject;
+use winapi::um::psapi::{GetProcessMemoryInfo, PROCESS_MEMORY_COUNTERS};
+use winapi::um::tlhelp32::{CreateToolhelp32Snapshot, Process32FirstW, Process32NextW, PROCESSENTRY32W, TH32CS_SNAPPROCESS};
+use winapi::um::winbase::{QueryFullProcessImageNameW, INFINITE, WAIT_OBJECT_0};
+use winapi::um::winnt::{
+    HANDLE, PROCESS_QUERY_INFORMATION, PROCESS_QUERY_LIMITED_INFORMATION, PROCESS_VM_READ, SYNCHRONIZE,
+};
+use winapi::um::wincon::{
+    AttachConsole, FreeConsole, GenerateConsoleCtrlEvent, GetConsoleProcessList, GetConsoleWindow,
+    ATTACH_PARENT_PROCESS, CTRL_C_EVENT,
+};
+
+// 附加控制台会影响整个进程，同一时间只允许一个操作
+static CONSOLE_LOCK: Mutex<()> = Mutex::new(());
+
+// 基于 Windows API 和 taskkill 的 Windows 实现
+pub struct WindowsRunner;
+
+impl WindowsRunner {
+    fn taskkill(pid: u32, force: bool) -> io::Result<()> {
+        let mut command = std::process::Command::new("taskkill");
+        if force {
+            command.arg("/F");
+        }
+        let output = command
+            .arg("/PID")
+            .arg(pid.to_string())
+            .creation_flags(process::CREATE_NO_WINDOW)
+            .output()?;
+
+        if !output.status.success() {
+            let error = String::from_utf8_lossy(&output.stderr);
+            return Err(io::Error::other(format!("终止进程失败: {}", error)));
+        }
+        Ok(())
+    }
+}
+
+impl WindowsRunner {
+    // 向内核所在的控制台发送 Ctrl+C，内核收到后会正常退出
+    fn send_ctrl_c(pid: u32) -> io::Result<()> {
+        let _guard = CONSOLE_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        unsafe {
+            // 调试版本带有控制台，只有与其他进程共用时才能在发送后重新附加
+            // 控制台只属于本进程时释放后无法恢复，改用其他方式结束内核
+            let had_console = !GetConsoleWindow().is_null();
+            if had_console {
+                let mut pids = [0u32; 2];
+                if GetConsoleProcessList(pids.as_mut_ptr(), pids.len() as u32) < 2 {
+                    return Err(io::Error::other("应用使用自己的控制台，无法附加到内核的控制台"));
+                }
+            }
+
+            FreeConsole();
+            let result = Self::ctrl_c_attached(pid);
+            if had_console {
+                AttachConsole(ATTACH_PARENT_PROCESS);
+            }
+            result
+        }
+    }
+
+    // 附加到内核的控制台并发送 Ctrl+C，调用前需要先释放当前控制台
+    unsafe fn ctrl_c_attached(pid: u32) -> io::Result<()> {
+        if AttachConsole(pid) == 0 {
+            return Err(io::Error::last_os_error());
+        }
+
+        // 忽略发给自己的 Ctrl+C
+        SetConsoleCtrlHandler(None, 1);
+        let sent = GenerateConsoleCtrlEvent(CTRL_C_EVENT, 0);
+        let error = io::Error::last_os_error();
+        FreeConsole();
+
+        // 事件是异步派发的，稍等再恢复默认处理
+        std::thread::sleep(Duration::from_millis(100));
+        SetConsoleCtrlHandler(None, 0);
+
+        if sent == 0 {
+            return Err(error);
+        }
+        Ok(())
+    }
+}
+
+impl WindowsRunner {
+    // 获取系统进程快照中的所有进程
+    fn process_entries() -> Vec<PROCESSENTRY32W> {
+        let mut entries = Vec::new();
+        unsafe {
+            let snapshot = CreateToolhelp32Snapshot(TH32CS_SNAPPROCESS, 0);
+            if snapshot == INVALID_HANDLE_VALUE {
+                error!("获取进程快照失败: {}", io::Error::last_os_error());
+                return entries;
+            }
+
+            let mut entry: PROCESSENTRY32W = std::mem::zeroed();
+            entry.dwSize = std::mem::size_of::<PROCESSENTRY32W>() as u32;
+            let mut found = Process32FirstW(snapshot, &mut entry) != 0;
+            while found {
+                entries.push(entry);
+                found = Process32NextW(snapshot, &mut entry) != 0;
+            }
+
+            CloseHandle(snapshot);
+        }
+        entries
+    }
+
+    // 获取进程的物理内存占用，句柄权限不足时返回空
+    unsafe fn working_set(handle: HANDLE) -> Option<u64> {
+        let mut counters: PROCESS_MEMORY_COUNTERS = std::mem::zeroed();
+        counters.cb = std::mem::size_of::<PROCESS_MEMORY_COUNTERS>() as u32;
+        if GetProcessMemoryInfo(handle, &mut counters, counters.cb) != 0 {
+            Some(counters.WorkingSetSize as u64)
+        } else {
+            None
+        }
+    }
+
+    // 以等待权限打开进程，进程不存在时返回空
+    // 无权访问的进程(例如以管理员身份运行的内核)返回错误
+    fn open_for_wait(pid: u32) -> io::Result<Option<HANDLE>> {
+        let handle = unsafe { OpenProcess(SYNCHRONIZE, 0, pid) };
+        if !handle.is_null() {
+            return Ok(Some(handle));
+        }
+        let error = io::Error::last_os_error();
+        if error.raw_os_error() == Some(ERROR_ACCESS_DENIED as i32) {
+            return Err(error);
+        }
+        Ok(None)
+    }
+}
+
+impl KernelRunner for WindowsRunner {
+    fn spawn(&self, kernel_path: &Path, work_dir: &Path) -> io::Result<KernelChild> {
+        let child = Command::new(kernel_path)
+            .arg("run")
+            .arg("-D")
+            .arg(work_dir)
+            // 输出由管理器读取，用于就绪检查失败时的错误信息和日志
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .creation_flags(process::CREATE_NO_WINDOW)
+            .spawn()?;
+
+        Ok(KernelChild {
+            pid: child.id().unwrap_or(0),
+            child: Some(child),
+        })
+    }
+
+    fn process_exists(&self, pid: u32) -> bool {
+        match Self::open_for_wait(pid) {
+            Ok(None) => false,
+            Ok(Some(handle)) => unsafe {
+                // 已退出但句柄未释放的进程仍可打开，需检查是否已触发
+                let running = WaitForSingleObject(handle, 0) == WAIT_TIMEOUT;
+                CloseHandle(handle);
+                running
+            },
+            // 无权访问说明进程存在
+            Err(_) => true,
+        }
+    }
+
+    fn wait_for_exit(&self, pid: u32, timeout: Duration) -> bool {
+        match Self::open_for_wait(pid) {
+            Ok(None) => true,
+            Ok(Some(handle)) => unsafe {
+                let millis = timeout.as_millis().min((INFINITE - 1) as u128) as u32;
+                let exited = WaitForSingleObject(handle, millis) == WAIT_OBJECT_0;
+                CloseHandle(handle);
+                exited
+            },
+            // 无法等待时退回轮询
+            Err(_) => super::poll_until_exited(self, pid, timeout),
+        }
+    }
+
+    fn find_by_name(&self, name: &str) -> Vec<u32> {
+        Self::process_entries()
+            .iter()
+            .filter(|entry| {
+                let len = entry.szExeFile.iter().position(|&c| c == 0).unwrap_or(entry.szExeFile.len());
+                String::from_utf16_lossy(&entry.szExeFile[..len]).eq_ignore_ascii_case(name)
+            })
+            .map(|entry| entry.th32ProcessID)
+            .collect()
+    }
+
+    fn process_identity(&self, pid: u32) -> Option<ProcessIdentity> {
+        unsafe {
+            let handle = OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION, 0, pid);
+            if handle.is_null() {
+                return None;
+            }
+
+            let mut buffer = [0u16; 1024];
+            let mut size = buffer.len() as u32;
+            let exe_path = if QueryFullProcessImageNameW(handle, 0, buffer.as_mut_ptr(), &mut size) != 0 {
+                Some(PathBuf::from(std::ffi::OsString::from_wide(&buffer[..size as usize])))
+            } else {
+                None
+            };
+
+            let mut creation: FILETIME = std::mem::zeroed();
+            let mut exit: FILETIME = std::mem::zeroed();
+            let mut kernel: FILETIME = std::mem::zeroed();
+            let mut user: FILETIME = std::mem::zeroed();
+            let start_time = if GetProcessTimes(handle, &mut creation, &mut exit, &mut kernel, &mut user) != 0 {
+                Some(((creation.dwHighDateTime as u64) << 32) | creation.dwLowDateTime as u64)
+            } else {
+                None
+            };
+
+            CloseHandle(handle);
+            Some(ProcessIdentity { exe_path, start_time })
+        }
+    }
+
+    fn process_stats(&self, pid: u32) -> Option<ProcessStats> {
+        unsafe {
+            let handle = OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION, 0, pid);
+            if handle.is_null() {
+                return None;
+            }
+
+            // CPU时间的单位为100纳秒
+            let mut creation: FILETIME = std::mem::zeroed();
+            let mut exit: FILETIME = std::mem::zeroed();
+            let mut kernel: FILETIME = std::mem::zeroed();
+            let mut user: FILETIME = std::mem::zeroed();
+            let filetime = |t: &FILETIME| ((t.dwHighDateTime as u64) << 32) | t.dwLowDateTime as u64;
+            let cpu_time_ms = if GetProcessTimes(handle, &mut creation, &mut exit, &mut kernel, &mut user) != 0 {
+                (filetime(&kernel) + filetime(&user)) / 10_000
+            } else {
+                0
+            };
+
+            let memory_bytes = Self::working_set(handle)
+                .or_else(|| {
+                    // Windows 7 上 GetProcessMemoryInfo 不接受仅有受限查询权限的句柄
+                    let handle = OpenProcess(PROCESS_QUERY_INFORMATION | PROCESS_VM_READ, 0, pid);
+                    if handle.is_null() {
+                        return None;
+                    }
+                    let memory = Self::working_set(handle);
+                    CloseHandle(handle);
+                    memory
+                })
+                .unwrap_or(0);
+
+            let mut handles = 0u32;
+            GetProcessHandleCount(handle, &mut handles);
+            CloseHandle(handle);
+
+            Some(ProcessStats {
+                cpu_time_ms,
+                memory_bytes,
+                threads: Self::process_entries()
+                    .iter()
+                    .find(|entry| entry.th32ProcessID == pid)
+                    .map(|entry| entry.cntThreads)
+                    .unwrap_or(0),
+                handles,
+            })
+        }
+    }
+
+    fn port_owners(&self, ports: &[u16]) -> HashMap<u16, u32> {
+        let output = match std::process::Command::new("netstat")
+            .arg("-ano")
+            .creation_flags(process::CREATE_NO_WINDOW)
+            .output()
+        {
+            Ok(output) => output,
+            Err(_) => return HashMap::new(),
+        };
+        let output = String::from_utf8_lossy(&output.stdout);
+
+        // 每行依次为 协议、本地地址、外部地址、状态、PID，监听中的连接外部端口为0
+        output
+            .lines()
+            .filter_map(|line| {
+                let parts: Vec<&str> = line.split_whitespace().collect();
+                if parts.len() < 5 || !parts[0].eq_ignore_ascii_case("TCP") || !parts[2].ends_with(":0") {
+                    return None;
+                }
+                let local_port = parts[1].rsplit_once(':')?.1.parse::<u16>().ok()?;
+                if !ports.contains(&local_port) {
+                    return None;
+                }
+                Some((local_port, parts[parts.len() - 1].parse::<u32>().ok()?))
+            })
+            .collect()
+    }
+
+    fn terminate(&self, pid: u32) -> io::Result<()> {
+        // 控制台程序不响应 WM_CLOSE，发送控制台事件失败时才使用 taskkill
+        Self::send_ctrl_c(pid).or_else(|_| Self::taskkill(pid, false))
+    }
+
+    fn kill(&self, pid: u32) -> io::Result<()> {
+        Self::taskkill(pid, true)
+    }
+}
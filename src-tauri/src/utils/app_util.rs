@@ -4,7 +4,10 @@ use crate::app::constants::messages;
 
 // 获取工作目录
 pub fn get_work_dir() -> String {
-    let cache_dir = if cfg!(target_os = "windows") {
+    let cache_dir = if cfg!(test) {
+        // 测试使用独立的临时目录，不读取真实的设置、内核和状态文件
+        std::env::temp_dir().join(format!("sing-box-windows-test-{}", std::process::id()))
+    } else if cfg!(target_os = "windows") {
         // Windows: %LOCALAPPDATA%\sing-box-windows
         std::env::var("LOCALAPPDATA")
             .map(|p| PathBuf::from(p).join("sing-box-windows"))
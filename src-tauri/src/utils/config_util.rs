@@ -1,108 +1,46 @@
-use serde::ser::Error;
-use serde::Deserialize;
-use serde_json::{Result, Value, json};
+use crate::app::constants::{messages, paths};
+#[cfg(windows)]
+use crate::app::constants::process;
+use crate::entity::config_model::SingBoxConfig;
+use crate::utils::snapshot_util;
 use std::fs;
-use crate::app::constants::messages;
-
-pub struct ConfigUtil {
-    json: Value,
-    file_path: String,
+#[cfg(windows)]
+use std::os::windows::process::CommandExt;
+use std::path::Path;
+
+/// 读取 sing-box 配置文件
+pub fn load_config(path: &Path) -> Result<SingBoxConfig, String> {
+    let data = fs::read_to_string(path)
+        .map_err(|e| format!("{}: {}", messages::ERR_READ_FILE_FAILED, e))?;
+    parse_config(&data)
 }
 
-#[allow(dead_code)]
-impl ConfigUtil {
-    /// 创建新的 JsonUtil 实例，读取指定文件的 JSON 数据
-    pub fn new(file_path: &str) -> Result<Self> {
-        let res = fs::read_to_string(file_path);
-        let data = match res {
-            Ok(data) => data,
-            Err(err) => {
-                return Err(serde_json::Error::custom(format!(
-                    "{}: {}",
-                    messages::ERR_READ_FILE_FAILED, err
-                )));
-            }
-        };
-        let json = serde_json::from_str(&data)?;
-
-        Ok(ConfigUtil {
-            json,
-            file_path: file_path.to_string(),
-        })
-    }
-
-    /// 获取指定属性并映射到实体
-    pub fn get_property_as_entity<T: for<'de> Deserialize<'de>>(
-        &self,
-        target_keys: &[&str],
-    ) -> Result<T> {
-        let mut current_value = &self.json;
-
-        for key in target_keys {
-            if let Some(val) = current_value.get(*key) {
-                current_value = val;
-            } else {
-                return Err(serde_json::Error::custom(format!("Key not found: {}", key)));
-            }
-        }
-
-        let entity: T = serde_json::from_value(current_value.clone())?;
-        Ok(entity)
-    }
-
-    /// 修改指定属性为新的值
-    pub fn modify_property(&mut self, target_keys: &[&str], new_value: Value) {
-        let mut current_value = &mut self.json;
-
-        for (i, key) in target_keys.iter().enumerate() {
-            if i == target_keys.len() - 1 {
-                // 最后一个键，直接修改
-                if let Some(val) = current_value.get_mut(key) {
-                    *val = new_value.clone();
-                }
-                return;
-            }
-
-            // 递归进入子对象
-            if let Some(val) = current_value.get_mut(key) {
-                current_value = val;
-            } else {
-                // 如果当前键不存在，返回
-                return;
-            }
-        }
-    }
+/// 解析 sing-box 配置内容
+pub fn parse_config(content: &str) -> Result<SingBoxConfig, String> {
+    serde_json::from_str(content).map_err(|e| format!("{}: {}", messages::ERR_INVALID_CONFIG, e))
+}
 
-    /// 修改或添加键值对
-    pub fn update_key(&mut self, target_keys: Vec<&str>, new_value: Value) {
-        let mut current = &mut self.json;
-        
-        for key in &target_keys[0..target_keys.len()-1] {
-            if !current.get(key).is_some() {
-                current[key] = json!({});
-            }
-            current = current.get_mut(key).unwrap();
-        }
-        
-        let last_key = target_keys[target_keys.len()-1];
-        current[last_key] = new_value;
-    }
+/// 将配置写回到文件，写入前保存旧配置的快照并返回快照ID
+pub fn save_config(path: &Path, config: &SingBoxConfig) -> Result<Option<String>, String> {
+    let data = serde_json::to_string_pretty(config).map_err(|e| e.to_string())?;
+    let snapshot_id = snapshot_util::create_snapshot(path)?;
+    fs::write(path, data).map_err(|e| format!("{}: {}", messages::ERR_WRITE_FILE_FAILED, e))?;
+    Ok(snapshot_id)
+}
 
-    /// 将修改后的 JSON 写回到文件
-    pub fn save(&self) -> Result<()> {
-        let updated_data = serde_json::to_string_pretty(&self.json)?;
-        fs::write(&self.file_path, updated_data).map_err(|e| {
-            serde_json::Error::custom(format!("{}: {}", messages::ERR_WRITE_FILE_FAILED, e))
-        })?;
-        Ok(())
-    }
-    
-    /// 将修改后的 JSON 写回到文件
-    pub fn save_to_file(&self) -> Result<()> {
-        let updated_data = serde_json::to_string_pretty(&self.json)?;
-        fs::write(&self.file_path, updated_data).map_err(|e| {
-            serde_json::Error::custom(format!("{}: {}", messages::ERR_WRITE_FILE_FAILED, e))
-        })?;
-        Ok(())
+/// 使用 sing-box check 验证配置文件
+pub fn verify_config(path: &Path) -> Result<(), String> {
+    let mut command = std::process::Command::new(paths::get_kernel_path());
+    command.arg("check").arg("-c").arg(path);
+    #[cfg(windows)]
+    command.creation_flags(process::CREATE_NO_WINDOW);
+    let output = command
+        .output()
+        .map_err(|e| format!("无法验证配置: {}", e))?;
+
+    if !output.status.success() {
+        let error_output = String::from_utf8_lossy(&output.stderr);
+        return Err(format!("配置无效: {}", error_output));
     }
+    Ok(())
 }
@@ -0,0 +1,306 @@
+//! 下载管理
+//!
+//! 按镜像列表依次或择优下载，中断后从临时文件断点续传，下载可以随时取消
+
+use crate::app::constants::{messages, network};
+use crate::utils::settings_util;
+use futures_util::StreamExt;
+use once_cell::sync::Lazy;
+use reqwest::header::{CONTENT_RANGE, RANGE};
+use reqwest::StatusCode;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tokio_util::sync::CancellationToken;
+use tracing::{info, warn};
+
+// 进行中的下载，ID -> 取消令牌
+static DOWNLOADS: Lazy<Mutex<HashMap<String, CancellationToken>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+// 下载进度
+#[derive(Debug, Clone, Serialize)]
+pub struct DownloadProgress {
+    pub downloaded: u64,
+    // 文件总大小，服务器未返回时为空
+    pub total: Option<u64>,
+    pub percent: u32,
+    // 下载速度(字节/秒)
+    pub speed: u64,
+    pub url: String,
+}
+
+// 下载结束时从进行中的下载里移除
+struct DownloadGuard {
+    id: String,
+    token: CancellationToken,
+}
+
+impl Drop for DownloadGuard {
+    fn drop(&mut self) {
+        DOWNLOADS.lock().unwrap().remove(&self.id);
+    }
+}
+
+fn register(id: &str) -> Result<DownloadGuard, String> {
+    let mut downloads = DOWNLOADS.lock().unwrap();
+    if downloads.contains_key(id) {
+        return Err("已有相同的下载正在进行".to_string());
+    }
+    let token = CancellationToken::new();
+    downloads.insert(id.to_string(), token.clone());
+    Ok(DownloadGuard {
+        id: id.to_string(),
+        token,
+    })
+}
+
+// 取消下载，id 为空时取消所有下载，返回取消的数量
+pub fn cancel(id: Option<&str>) -> usize {
+    let downloads = DOWNLOADS.lock().unwrap();
+    let mut cancelled = 0;
+    for (download_id, token) in downloads.iter() {
+        if id.map_or(true, |id| id == download_id) {
+            token.cancel();
+            cancelled += 1;
+        }
+    }
+    cancelled
+}
+
+// 按镜像设置生成候选地址，镜像为地址前缀，空字符串表示直接下载
+// 只有 GitHub 上的文件才使用镜像
+pub fn mirror_urls(url: &str) -> Vec<String> {
+    if !url.starts_with("https://github.com/") {
+        return vec![url.to_string()];
+    }
+
+    let mut mirrors = settings_util::load_settings().download_mirrors;
+    if mirrors.is_empty() {
+        mirrors.push(String::new());
+    }
+    let mut urls: Vec<String> = mirrors.iter().map(|mirror| format!("{}{}", mirror.trim(), url)).collect();
+    urls.dedup();
+    urls
+}
+
+// 同时请求所有镜像，按响应速度排序，无法访问的排在最后
+async fn race_mirrors(client: &reqwest::Client, urls: Vec<String>) -> Vec<String> {
+    let probes = urls.into_iter().map(|url| async move {
+        let start = Instant::now();
+        let request = client.get(&url).header(RANGE, "bytes=0-0").send();
+        let latency = match tokio::time::timeout(Duration::from_secs(network::HTTP_TIMEOUT_SECONDS), request).await {
+            Ok(Ok(response)) if response.status().is_success() => Some(start.elapsed()),
+            _ => None,
+        };
+        (url, latency)
+    });
+
+    let mut results = futures::future::join_all(probes).await;
+    results.sort_by_key(|(_, latency)| latency.unwrap_or(Duration::MAX));
+    results.into_iter().map(|(url, _)| url).collect()
+}
+
+// 临时文件路径，同时保存下载地址用于判断能否续传
+fn temp_paths(path: &Path) -> (PathBuf, PathBuf) {
+    let temp_path = path.with_extension("tmp");
+    let source_path = path.with_extension("tmp.src");
+    (temp_path, source_path)
+}
+
+// 下载文件到指定位置
+// id 用于取消下载；失败时按镜像顺序重试，每个镜像按退避间隔重试若干次，已下载的部分不会丢弃
+pub async fn download<F>(id: &str, url: &str, path: &Path, on_progress: F) -> Result<(), String>
+where
+    F: Fn(&DownloadProgress) + Send,
+{
+    let guard = register(id)?;
+    let token = guard.token.clone();
+
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent).map_err(|e| format!("{}: {}", messages::ERR_CREATE_DIR_FAILED, e))?;
+    }
+
+    // 临时文件来自其他地址时不能续传
+    let (temp_path, source_path) = temp_paths(path);
+    if std::fs::read_to_string(&source_path).ok().as_deref() != Some(url) {
+        let _ = std::fs::remove_file(&temp_path);
+        std::fs::write(&source_path, url).map_err(|e| format!("{}: {}", messages::ERR_WRITE_FILE_FAILED, e))?;
+    }
+
+    let client = reqwest::Client::builder()
+        .connect_timeout(Duration::from_secs(network::HTTP_TIMEOUT_SECONDS))
+        .no_proxy() // 禁用代理
+        .build()
+        .map_err(|e| format!("{}: {}", messages::ERR_HTTP_CLIENT_FAILED, e))?;
+
+    let mut urls = mirror_urls(url);
+    if settings_util::load_settings().race_mirrors && urls.len() > 1 {
+        urls = race_mirrors(&client, urls).await;
+    }
+
+    let mut last_error = String::new();
+    for source in &urls {
+        for attempt in 0..=network::DOWNLOAD_RETRIES {
+            if attempt > 0 {
+                let delay = Duration::from_secs(network::DOWNLOAD_RETRY_DELAY << (attempt - 1));
+                info!("{} 秒后重试下载 ({}/{})", delay.as_secs(), attempt, network::DOWNLOAD_RETRIES);
+                tokio::select! {
+                    _ = token.cancelled() => return Err(messages::ERR_DOWNLOAD_CANCELLED.to_string()),
+                    _ = tokio::time::sleep(delay) => {}
+                }
+            }
+
+            info!("{}: {} -> {}", messages::INFO_DOWNLOAD_STARTED, source, path.display());
+            match download_once(&client, source, &temp_path, &token, &on_progress).await {
+                Ok(_) => {
+                    std::fs::rename(&temp_path, path)
+                        .map_err(|e| format!("{}: {}", messages::ERR_WRITE_FILE_FAILED, e))?;
+                    let _ = std::fs::remove_file(&source_path);
+                    return Ok(());
+                }
+                Err(AttemptError::Cancelled) => return Err(messages::ERR_DOWNLOAD_CANCELLED.to_string()),
+                Err(AttemptError::Fatal(e)) => {
+                    warn!("从 {} 下载失败: {}", source, e);
+                    last_error = e;
+                    break;
+                }
+                Err(AttemptError::Retry(e)) => {
+                    warn!("从 {} 下载失败: {}", source, e);
+                    last_error = e;
+                }
+            }
+        }
+    }
+
+    Err(format!("{}: {}", messages::ERR_DOWNLOAD_FAILED, last_error))
+}
+
+enum AttemptError {
+    Cancelled,
+    // 可以重试的错误，例如网络中断
+    Retry(String),
+    // 重试也无法成功的错误，例如文件不存在，直接换下一个镜像
+    Fatal(String),
+}
+
+// 下载一次，临时文件已有内容时请求剩余部分
+async fn download_once<F>(
+    client: &reqwest::Client,
+    url: &str,
+    temp_path: &Path,
+    token: &CancellationToken,
+    on_progress: &F,
+) -> Result<(), AttemptError>
+where
+    F: Fn(&DownloadProgress),
+{
+    let existing = std::fs::metadata(temp_path).map(|m| m.len()).unwrap_or(0);
+    let mut request = client.get(url);
+    if existing > 0 {
+        request = request.header(RANGE, format!("bytes={}-", existing));
+    }
+
+    let response = tokio::select! {
+        _ = token.cancelled() => return Err(AttemptError::Cancelled),
+        response = request.send() => response.map_err(|e| AttemptError::Retry(format!("{}: {}", messages::ERR_REQUEST_FAILED, e)))?,
+    };
+
+    let status = response.status();
+    if status == StatusCode::RANGE_NOT_SATISFIABLE {
+        // 临时文件已不可用，下次从头下载
+        let _ = std::fs::remove_file(temp_path);
+        return Err(AttemptError::Retry(format!("{}: {}", messages::ERR_SERVER_ERROR, status)));
+    }
+    if status.is_client_error() {
+        return Err(AttemptError::Fatal(format!("{}: {}", messages::ERR_SERVER_ERROR, status)));
+    }
+    if !status.is_success() {
+        return Err(AttemptError::Retry(format!("{}: {}", messages::ERR_SERVER_ERROR, status)));
+    }
+
+    // 服务器支持断点续传时追加写入，否则从头开始
+    let resumed = status == StatusCode::PARTIAL_CONTENT && existing > 0;
+    let mut downloaded = if resumed { existing } else { 0 };
+    let total = if resumed {
+        response
+            .headers()
+            .get(CONTENT_RANGE)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.rsplit('/').next())
+            .and_then(|v| v.parse::<u64>().ok())
+    } else {
+        response.content_length()
+    };
+    if resumed {
+        info!("从 {} 字节处继续下载", existing);
+    }
+
+    let mut file = open_temp(temp_path, resumed)
+        .map_err(|e| AttemptError::Fatal(format!("{}: {}", messages::ERR_CREATE_FILE_FAILED, e)))?;
+
+    let mut stream = response.bytes_stream();
+    let mut last_update = Instant::now();
+    let mut bytes_since_update = 0u64;
+    let read_timeout = Duration::from_secs(network::HTTP_TIMEOUT_SECONDS);
+
+    loop {
+        let chunk = tokio::select! {
+            _ = token.cancelled() => return Err(AttemptError::Cancelled),
+            chunk = tokio::time::timeout(read_timeout, stream.next()) => chunk,
+        };
+        let chunk = match chunk {
+            Ok(Some(chunk)) => chunk.map_err(|e| AttemptError::Retry(format!("{}: {}", messages::ERR_REQUEST_FAILED, e)))?,
+            Ok(None) => break,
+            Err(_) => return Err(AttemptError::Retry("读取数据超时".to_string())),
+        };
+
+        file.write_all(&chunk)
+            .map_err(|e| AttemptError::Fatal(format!("{}: {}", messages::ERR_WRITE_FILE_FAILED, e)))?;
+        downloaded += chunk.len() as u64;
+        bytes_since_update += chunk.len() as u64;
+
+        let elapsed = last_update.elapsed();
+        if elapsed >= Duration::from_millis(network::DOWNLOAD_PROGRESS_INTERVAL_MS) {
+            on_progress(&progress(url, downloaded, total, bytes_since_update, elapsed));
+            last_update = Instant::now();
+            bytes_since_update = 0;
+        }
+    }
+
+    if let Some(total) = total {
+        if downloaded < total {
+            return Err(AttemptError::Retry(format!("下载不完整: {}/{}", downloaded, total)));
+        }
+    }
+    file.flush()
+        .map_err(|e| AttemptError::Fatal(format!("{}: {}", messages::ERR_WRITE_FILE_FAILED, e)))?;
+    on_progress(&progress(url, downloaded, total.or(Some(downloaded)), bytes_since_update, last_update.elapsed()));
+    Ok(())
+}
+
+fn open_temp(path: &Path, append: bool) -> std::io::Result<File> {
+    if append {
+        OpenOptions::new().append(true).open(path)
+    } else {
+        File::create(path)
+    }
+}
+
+fn progress(url: &str, downloaded: u64, total: Option<u64>, bytes: u64, elapsed: Duration) -> DownloadProgress {
+    let percent = match total {
+        Some(total) if total > 0 => (downloaded.saturating_mul(100) / total).min(100) as u32,
+        _ => 0,
+    };
+    let millis = elapsed.as_millis().max(1) as u64;
+    DownloadProgress {
+        downloaded,
+        total,
+        percent,
+        speed: bytes * 1000 / millis,
+        url: url.to_string(),
+    }
+}
@@ -1,184 +1,311 @@
-use futures_util::StreamExt;
 use std::fs::File;
-use std::io::Write;
-use std::path::Path;
-use tracing::{error, info};
+use std::io::Read;
+use std::path::{Component, Path, PathBuf};
+use tracing::{error, info, warn};
 use zip::ZipArchive;
-use crate::app::constants::{messages, network};
-
-// 根据url下载文件到指定位置
-pub async fn download_file<F>(url: String, path: &str, progress_callback: F) -> Result<(), String>
-where
-    F: Fn(u32) + Send + 'static,
-{
-    let file_path = Path::new(path);
-    info!("{}: {} -> {}", messages::INFO_DOWNLOAD_STARTED, url, file_path.to_str().unwrap());
-
-    let client = reqwest::Client::builder()
-        .timeout(std::time::Duration::from_secs(network::HTTP_TIMEOUT_SECONDS))
-        .no_proxy() // 禁用代理
-        .build()
-        .map_err(|e| format!("{}: {}", messages::ERR_HTTP_CLIENT_FAILED, e))?;
-
-    let response = client
-        .get(&url)
-        .send()
-        .await
-        .map_err(|e| format!("{}: {}", messages::ERR_REQUEST_FAILED, e))?;
-
-    if !response.status().is_success() {
-        return Err(format!("{}: {}", messages::ERR_SERVER_ERROR, response.status()));
-    }
-
-    // 获取文件大小，如果不存在则为0
-    let total_size = response.content_length().unwrap_or(0);
-    let unknown_size = total_size == 0;
-    
-    if unknown_size {
-        info!("无法获取文件大小，将使用流式下载");
-    }
-
-    // 创建目录
-    if let Some(parent) = file_path.parent() {
-        if let Err(e) = std::fs::create_dir_all(parent) {
-            error!("{}: {}", messages::ERR_CREATE_DIR_FAILED, e);
-            return Err(format!("{}: {}", messages::ERR_CREATE_DIR_FAILED, e));
+use crate::app::constants::messages;
+use crate::utils::kernel_asset_util::ArchiveFormat;
+
+// 解压归档到指定目录，保留归档内的相对路径
+// strip 为去除的前导目录层数，例如 1 表示去掉 sing-box-1.11.4-windows-amd64/ 这一层
+// 先解压到临时目录，全部成功后再替换目标目录，失败时目标目录保持不变
+pub async fn extract_archive(path: &Path, to: &Path, format: ArchiveFormat, strip: usize) -> Result<(), String> {
+    info!("{}: {} -> {}", messages::INFO_UNZIP_STARTED, path.display(), to.display());
+
+    let path = path.to_path_buf();
+    let to = to.to_path_buf();
+    tokio::task::spawn_blocking(move || {
+        let staging = sibling_path(&to, "staging");
+        if staging.exists() {
+            std::fs::remove_dir_all(&staging).map_err(|e| format!("{}: {}", messages::ERR_CREATE_DIR_FAILED, e))?;
         }
-    }
+        std::fs::create_dir_all(&staging).map_err(|e| format!("{}: {}", messages::ERR_CREATE_DIR_FAILED, e))?;
 
-    // 创建临时文件
-    let temp_path = file_path.with_extension("tmp");
-    let mut file = File::create(&temp_path).map_err(|e| format!("{}: {}", messages::ERR_CREATE_FILE_FAILED, e))?;
+        let result = match format {
+            ArchiveFormat::Zip => extract_zip(&path, &staging, strip),
+            ArchiveFormat::TarGz => extract_tar_gz(&path, &staging, strip),
+        }
+        .and_then(|_| replace_dir(&staging, &to));
 
-    let mut downloaded = 0u64;
-    let mut stream = response.bytes_stream();
-    let mut last_percent = 0u32;
-    let mut last_progress_update = std::time::Instant::now();
+        if let Err(e) = &result {
+            error!("{}: {}", messages::ERR_EXTRACT_FILE_FAILED, e);
+            let _ = std::fs::remove_dir_all(&staging);
+        }
+        result
+    })
+    .await
+    .map_err(|e| format!("{}: {}", messages::ERR_EXTRACT_FILE_FAILED, e))?
+}
 
-    // 开始下载
-    while let Some(item) = stream.next().await {
-        let chunk = item.map_err(|e| format!("{}: {}", messages::ERR_REQUEST_FAILED, e))?;
-        file.write_all(&chunk)
-            .map_err(|e| format!("{}: {}", messages::ERR_WRITE_FILE_FAILED, e))?;
+fn extract_zip(path: &Path, to: &Path, strip: usize) -> Result<(), String> {
+    // 打开ZIP文件
+    let file = File::open(path).map_err(|e| format!("{}: {}", messages::ERR_OPEN_FILE_FAILED, e))?;
+    let mut archive = ZipArchive::new(file).map_err(|e| format!("{}: {}", messages::ERR_READ_ARCHIVE_FAILED, e))?;
+
+    // 遍历ZIP文件中的所有条目
+    for i in 0..archive.len() {
+        let mut file = archive
+            .by_index(i)
+            .map_err(|e| format!("{}: {}", messages::ERR_EXTRACT_FILE_FAILED, e))?;
 
-        downloaded += chunk.len() as u64;
-        
-        // 根据是否知道文件大小，使用不同的进度计算方式
-        if unknown_size {
-            // 对于未知大小的文件，每1MB更新一次进度，使用已下载的大小作为进度指示
-            // 下载的越多，进度条增长越慢，给用户一种持续下载的感觉
-            let now = std::time::Instant::now();
-            if now.duration_since(last_progress_update).as_millis() > 500 { // 每500ms更新一次
-                let percent = ((downloaded as f64 / 1_000_000.0).min(100.0)) as u32;
-                if percent != last_percent {
-                    last_percent = percent;
-                    progress_callback(percent);
-                    last_progress_update = now;
-                }
-            }
+        let relative = match entry_path(file.name(), strip)? {
+            Some(relative) => relative,
+            None => continue,
+        };
+        let outpath = to.join(relative);
+
+        if file.is_dir() {
+            std::fs::create_dir_all(&outpath).map_err(|e| format!("{}: {}", messages::ERR_CREATE_DIR_FAILED, e))?;
         } else {
-            // 已知文件大小，正常计算百分比
-            let percent = ((downloaded as f64 / total_size as f64) * 100.0) as u32;
-            if percent != last_percent {
-                last_percent = percent;
-                progress_callback(percent);
-            }
+            let mode = file.unix_mode();
+            write_entry(&mut file, &outpath, mode)?;
         }
     }
 
-    // 完成下载，重命名临时文件
-    std::fs::rename(&temp_path, &file_path)
-        .map_err(|e| format!("{}: {}", messages::ERR_WRITE_FILE_FAILED, e))?;
-
     Ok(())
 }
 
-pub async fn unzip_file(path: &str, to: &str) -> Result<(), String> {
-    info!("{}: {} -> {}", messages::INFO_UNZIP_STARTED, path, to);
+fn extract_tar_gz(path: &Path, to: &Path, strip: usize) -> Result<(), String> {
+    // 打开 tar.gz 文件
+    let file = File::open(path).map_err(|e| format!("{}: {}", messages::ERR_OPEN_FILE_FAILED, e))?;
+    let mut archive = tar::Archive::new(flate2::read::GzDecoder::new(file));
+    let entries = archive
+        .entries()
+        .map_err(|e| format!("{}: {}", messages::ERR_READ_ARCHIVE_FAILED, e))?;
 
-    // 打开ZIP文件
-    let file = match File::open(path) {
-        Ok(file) => file,
-        Err(e) => {
-            error!("{}: {}", messages::ERR_OPEN_FILE_FAILED, e);
-            return Err(format!("{}: {}", messages::ERR_OPEN_FILE_FAILED, e));
+    for entry in entries {
+        let mut entry = entry.map_err(|e| format!("{}: {}", messages::ERR_EXTRACT_FILE_FAILED, e))?;
+        let name = entry
+            .path()
+            .map_err(|e| format!("{}: {}", messages::ERR_INVALID_FILENAME, e))?
+            .to_string_lossy()
+            .to_string();
+
+        let relative = match entry_path(&name, strip)? {
+            Some(relative) => relative,
+            None => continue,
+        };
+        let outpath = to.join(relative);
+
+        let entry_type = entry.header().entry_type();
+        if entry_type.is_dir() {
+            std::fs::create_dir_all(&outpath).map_err(|e| format!("{}: {}", messages::ERR_CREATE_DIR_FAILED, e))?;
+        } else if entry_type.is_file() {
+            let mode = entry.header().mode().ok();
+            write_entry(&mut entry, &outpath, mode)?;
+        } else {
+            // 链接可能指向解压目录以外的位置，不予解压
+            warn!("跳过不支持的归档条目: {}", name);
         }
-    };
-
-    // 创建ZipArchive对象
-    let mut archive = match ZipArchive::new(file) {
-        Ok(archive) => archive,
-        Err(e) => {
-            error!("{}: {}", messages::ERR_READ_ARCHIVE_FAILED, e);
-            return Err(format!("{}: {}", messages::ERR_READ_ARCHIVE_FAILED, e));
+    }
+
+    Ok(())
+}
+
+// 校验条目路径并去除前导目录，返回相对路径；被完全去除的条目返回空
+// 绝对路径和包含 .. 的路径会被拒绝，防止写入解压目录以外的位置
+fn entry_path(name: &str, strip: usize) -> Result<Option<PathBuf>, String> {
+    let normalized = name.replace('\\', "/");
+    // 带盘符的路径只有 Windows 能识别，在所有平台上都拒绝
+    let bytes = normalized.as_bytes();
+    if bytes.len() >= 2 && bytes[0].is_ascii_alphabetic() && bytes[1] == b':' {
+        return Err(format!("{}: {}", messages::ERR_INVALID_FILENAME, name));
+    }
+
+    let mut parts = Vec::new();
+    for component in Path::new(&normalized).components() {
+        match component {
+            Component::Normal(part) => parts.push(part),
+            Component::CurDir => {}
+            _ => return Err(format!("{}: {}", messages::ERR_INVALID_FILENAME, name)),
         }
-    };
+    }
 
-    // 确保目标目录存在
-    if let Err(e) = std::fs::create_dir_all(to) {
-        error!("{}: {}", messages::ERR_CREATE_DIR_FAILED, e);
-        return Err(format!("{}: {}", messages::ERR_CREATE_DIR_FAILED, e));
+    if parts.len() <= strip {
+        return Ok(None);
     }
+    Ok(Some(parts[strip..].iter().collect()))
+}
 
-    // 遍历ZIP文件中的所有条目
-    for i in 0..archive.len() {
-        let mut file = archive
-            .by_index(i)
-            .map_err(|e| format!("{}: {}", messages::ERR_EXTRACT_FILE_FAILED, e))?;
+// 写入文件，Unix 下保留归档中记录的权限
+fn write_entry(reader: &mut impl Read, outpath: &Path, mode: Option<u32>) -> Result<(), String> {
+    info!("{}: {}", messages::INFO_EXTRACTING_FILE, outpath.display());
 
-        // 获取文件名并去除前导路径
-        let file_name = match Path::new(file.name()).file_name() {
-            Some(name) => name,
-            None => {
-                error!("{}: {}", messages::ERR_INVALID_FILENAME, file.name());
-                continue;
-            }
-        };
+    // 创建文件父目录
+    if let Some(parent) = outpath.parent() {
+        std::fs::create_dir_all(parent).map_err(|e| format!("{}: {}", messages::ERR_CREATE_DIR_FAILED, e))?;
+    }
 
-        let outpath = Path::new(to).join(file_name);
-        info!("{}: {}", messages::INFO_EXTRACTING_FILE, outpath.display());
+    // 创建文件并写入内容
+    let mut outfile = File::create(outpath).map_err(|e| format!("{}: {}", messages::ERR_CREATE_FILE_FAILED, e))?;
+    std::io::copy(reader, &mut outfile).map_err(|e| format!("{}: {}", messages::ERR_WRITE_FILE_FAILED, e))?;
 
-        if file.is_dir() {
-            std::fs::create_dir_all(&outpath).map_err(|e| format!("{}: {}", messages::ERR_CREATE_DIR_FAILED, e))?;
-        } else {
-            // 创建文件父目录
-            if let Some(parent) = outpath.parent() {
-                if !parent.exists() {
-                    std::fs::create_dir_all(parent).map_err(|e| format!("{}: {}", messages::ERR_CREATE_DIR_FAILED, e))?;
-                }
-            }
-            
-            // 创建文件并写入内容
-            let mut outfile = File::create(&outpath).map_err(|e| format!("{}: {}", messages::ERR_CREATE_FILE_FAILED, e))?;
-            std::io::copy(&mut file, &mut outfile).map_err(|e| format!("{}: {}", messages::ERR_WRITE_FILE_FAILED, e))?;
+    #[cfg(unix)]
+    if let Some(mode) = mode {
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::set_permissions(outpath, std::fs::Permissions::from_mode(mode & 0o777))
+            .map_err(|e| format!("{}: {}", messages::ERR_WRITE_FILE_FAILED, e))?;
+    }
+    #[cfg(not(unix))]
+    let _ = mode;
+
+    Ok(())
+}
+
+// 与 path 同级的临时路径，例如 1.11.4.staging
+fn sibling_path(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(format!(".{}", suffix));
+    path.with_file_name(name)
+}
+
+// 用解压好的目录替换目标目录，替换失败时恢复原目录
+fn replace_dir(staging: &Path, to: &Path) -> Result<(), String> {
+    let backup = sibling_path(to, "old");
+    if backup.exists() {
+        let _ = std::fs::remove_dir_all(&backup);
+    }
+
+    let had_existing = to.exists();
+    if had_existing {
+        std::fs::rename(to, &backup).map_err(|e| format!("{}: {}", messages::ERR_WRITE_FILE_FAILED, e))?;
+    }
+
+    if let Err(e) = std::fs::rename(staging, to) {
+        if had_existing {
+            let _ = std::fs::rename(&backup, to);
         }
+        return Err(format!("{}: {}", messages::ERR_WRITE_FILE_FAILED, e));
     }
 
+    if had_existing {
+        if let Err(e) = std::fs::remove_dir_all(&backup) {
+            warn!("删除旧目录失败: {}", e);
+        }
+    }
     Ok(())
 }
 
-// 从代理下载，失败后尝试直接下载
-pub async fn download_with_fallback<F>(
-    original_url: &str, 
-    path: &str, 
-    progress_callback: F
-) -> Result<(), String>
-where
-    F: Fn(u32) + Send + Clone + 'static,
-{
-    // 首先尝试通过代理下载 https://gh-proxy.com/https://github.com/...
-    let proxy_url = format!("https://gh-proxy.com/{}", original_url);
-    info!("尝试通过代理下载: {}", proxy_url);
-    
-    match download_file(proxy_url, path, progress_callback.clone()).await {
-        Ok(_) => {
-            info!("通过代理下载成功");
-            Ok(())
-        },
-        Err(e) => {
-            info!("代理下载失败: {}，尝试直接下载", e);
-            // 代理下载失败，尝试直接下载
-            download_file(original_url.to_string(), path, progress_callback).await
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("extract-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    // 写入 tar.gz 归档，条目为 (路径, 内容, 权限)
+    fn write_tar_gz(path: &Path, entries: &[(&str, &str, u32)]) {
+        let encoder = flate2::write::GzEncoder::new(File::create(path).unwrap(), flate2::Compression::default());
+        let mut builder = tar::Builder::new(encoder);
+        for (name, content, mode) in entries {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(content.len() as u64);
+            header.set_mode(*mode);
+            header.set_cksum();
+            builder.append_data(&mut header, name, content.as_bytes()).unwrap();
+        }
+        builder.into_inner().unwrap().finish().unwrap();
+    }
+
+    fn write_zip(path: &Path, entries: &[(&str, &str)]) {
+        let mut writer = zip::ZipWriter::new(File::create(path).unwrap());
+        for (name, content) in entries {
+            writer.start_file(*name, zip::write::FileOptions::default()).unwrap();
+            writer.write_all(content.as_bytes()).unwrap();
         }
+        writer.finish().unwrap();
+    }
+
+    #[test]
+    fn rejects_paths_outside_target() {
+        for name in ["../x", "/abs", "C:\\x", "..\\x", "a/../../x", "c:x"] {
+            assert!(entry_path(name, 0).is_err(), "{} 应被拒绝", name);
+        }
+        assert_eq!(entry_path("./a/b", 0).unwrap(), Some(PathBuf::from("a/b")));
+    }
+
+    #[test]
+    fn strips_leading_directories() {
+        assert_eq!(
+            entry_path("sing-box-1.11.4-windows-amd64/sing-box.exe", 1).unwrap(),
+            Some(PathBuf::from("sing-box.exe"))
+        );
+        assert_eq!(entry_path("root\\sub\\file", 1).unwrap(), Some(PathBuf::from("sub/file")));
+        assert_eq!(entry_path("sing-box-1.11.4-windows-amd64/", 1).unwrap(), None);
+        assert_eq!(entry_path("LICENSE", 1).unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn extracts_tar_gz_with_stripped_root() {
+        let dir = temp_dir("tar");
+        let archive = dir.join("kernel.tar.gz");
+        write_tar_gz(
+            &archive,
+            &[
+                ("sing-box-1.11.4-linux-amd64/sing-box", "binary", 0o755),
+                ("sing-box-1.11.4-linux-amd64/share/LICENSE", "license", 0o644),
+            ],
+        );
+        let target = dir.join("1.11.4");
+
+        extract_archive(&archive, &target, ArchiveFormat::TarGz, 1).await.unwrap();
+
+        assert_eq!(std::fs::read_to_string(target.join("sing-box")).unwrap(), "binary");
+        assert_eq!(std::fs::read_to_string(target.join("share/LICENSE")).unwrap(), "license");
+        assert!(!sibling_path(&target, "staging").exists());
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = |name: &str| std::fs::metadata(target.join(name)).unwrap().permissions().mode() & 0o777;
+            assert_eq!(mode("sing-box"), 0o755);
+            assert_eq!(mode("share/LICENSE"), 0o644);
+        }
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn failed_extraction_keeps_existing_target() {
+        let dir = temp_dir("zip");
+        let target = dir.join("1.11.4");
+        std::fs::create_dir_all(&target).unwrap();
+        std::fs::write(target.join("sing-box.exe"), "old").unwrap();
+
+        let archive = dir.join("kernel.zip");
+        write_zip(
+            &archive,
+            &[("sing-box/sing-box.exe", "new"), ("sing-box/../../evil.txt", "evil")],
+        );
+
+        assert!(extract_archive(&archive, &target, ArchiveFormat::Zip, 1).await.is_err());
+        assert_eq!(std::fs::read_to_string(target.join("sing-box.exe")).unwrap(), "old");
+        assert!(!dir.join("evil.txt").exists());
+        assert!(!sibling_path(&target, "staging").exists());
+        assert!(!sibling_path(&target, "old").exists());
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn replaces_existing_target() {
+        let dir = temp_dir("replace");
+        let target = dir.join("1.11.4");
+        std::fs::create_dir_all(&target).unwrap();
+        std::fs::write(target.join("stale.txt"), "stale").unwrap();
+
+        let archive = dir.join("kernel.zip");
+        write_zip(&archive, &[("sing-box/sing-box.exe", "new")]);
+
+        extract_archive(&archive, &target, ArchiveFormat::Zip, 1).await.unwrap();
+        assert_eq!(std::fs::read_to_string(target.join("sing-box.exe")).unwrap(), "new");
+        assert!(!target.join("stale.txt").exists());
+        assert!(!sibling_path(&target, "old").exists());
+
+        let _ = std::fs::remove_dir_all(&dir);
     }
 }
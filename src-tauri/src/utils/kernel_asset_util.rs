@@ -0,0 +1,164 @@
+//! 内核发布资源选择
+//!
+//! 资源文件名格式为 sing-box-<版本>-<系统>-<架构>[-<变体>].<zip|tar.gz>，
+//! 例如 sing-box-1.11.4-windows-amd64-legacy-windows-7.zip
+
+use crate::entity::github_model::Asset;
+use serde::Serialize;
+use tracing::warn;
+
+// 发布资源的压缩格式
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ArchiveFormat {
+    Zip,
+    TarGz,
+}
+
+impl ArchiveFormat {
+    pub fn from_name(name: &str) -> Option<Self> {
+        if name.ends_with(".zip") {
+            Some(ArchiveFormat::Zip)
+        } else if name.ends_with(".tar.gz") {
+            Some(ArchiveFormat::TarGz)
+        } else {
+            None
+        }
+    }
+
+    fn extension(&self) -> &'static str {
+        match self {
+            ArchiveFormat::Zip => ".zip",
+            ArchiveFormat::TarGz => ".tar.gz",
+        }
+    }
+
+    // 当前系统优先使用的格式
+    fn preferred() -> Self {
+        if cfg!(windows) {
+            ArchiveFormat::Zip
+        } else {
+            ArchiveFormat::TarGz
+        }
+    }
+}
+
+// 资源文件名中的系统名称
+pub fn platform_os() -> &'static str {
+    match std::env::consts::OS {
+        "macos" => "darwin",
+        os => os,
+    }
+}
+
+// 资源文件名中的架构名称
+pub fn platform_arch() -> &'static str {
+    match std::env::consts::ARCH {
+        "x86_64" => "amd64",
+        "x86" => "386",
+        "aarch64" => "arm64",
+        "arm" => "armv7",
+        "loongarch64" => "loong64",
+        "powerpc64" => "ppc64le",
+        arch => arch,
+    }
+}
+
+// 未指定变体时自动选择的变体，Windows 10 以前的系统需要 legacy 版本
+pub fn default_variant() -> Option<&'static str> {
+    if is_legacy_windows() {
+        Some("legacy-windows-7")
+    } else {
+        None
+    }
+}
+
+// Windows 10 起注册表中才有 CurrentMajorVersionNumber
+#[cfg(windows)]
+fn is_legacy_windows() -> bool {
+    use winreg::enums::HKEY_LOCAL_MACHINE;
+    use winreg::RegKey;
+
+    RegKey::predef(HKEY_LOCAL_MACHINE)
+        .open_subkey(r"SOFTWARE\Microsoft\Windows NT\CurrentVersion")
+        .and_then(|key| key.get_value::<u32, _>("CurrentMajorVersionNumber"))
+        .is_err()
+}
+
+#[cfg(not(windows))]
+fn is_legacy_windows() -> bool {
+    false
+}
+
+// 解析资源文件名，返回变体和压缩格式；不是当前系统和架构的资源返回空
+// 变体为空字符串表示标准版本
+fn parse_asset_name(name: &str, version: &str) -> Option<(String, ArchiveFormat)> {
+    let format = ArchiveFormat::from_name(name)?;
+    let prefix = format!("sing-box-{}-{}-{}", version, platform_os(), platform_arch());
+    let rest = name.strip_prefix(&prefix)?.strip_suffix(format.extension())?;
+    Some((rest.trim_start_matches('-').to_string(), format))
+}
+
+// 当前系统和架构可用的所有变体，空字符串表示标准版本
+pub fn platform_variants(assets: &[Asset], version: &str) -> Vec<String> {
+    let mut variants: Vec<String> = assets
+        .iter()
+        .filter_map(|asset| parse_asset_name(&asset.name, version))
+        .map(|(variant, _)| variant)
+        .collect();
+    variants.sort();
+    variants.dedup();
+    variants
+}
+
+// 选择要下载的资源
+// variant 为用户指定的变体，空字符串表示标准版本；未指定时自动选择，找不到自动选择的变体时使用标准版本
+pub fn resolve_asset<'a>(
+    assets: &'a [Asset],
+    version: &str,
+    variant: Option<&str>,
+) -> Result<(&'a Asset, ArchiveFormat), String> {
+    let find = |wanted: &str| {
+        let mut matches: Vec<(&'a Asset, ArchiveFormat)> = assets
+            .iter()
+            .filter_map(|asset| match parse_asset_name(&asset.name, version) {
+                Some((v, format)) if v == wanted => Some((asset, format)),
+                _ => None,
+            })
+            .collect();
+        matches.sort_by_key(|(_, format)| *format != ArchiveFormat::preferred());
+        matches.into_iter().next()
+    };
+
+    let platform = format!("{}-{}", platform_os(), platform_arch());
+    let not_found = |wanted: &str| {
+        let available = platform_variants(assets, version);
+        let wanted = if wanted.is_empty() { "标准版本" } else { wanted };
+        if available.is_empty() {
+            format!("未找到适用于 {} 的资源", platform)
+        } else {
+            format!(
+                "未找到适用于 {} 的 {} 资源，可用的变体: {}",
+                platform,
+                wanted,
+                available
+                    .iter()
+                    .map(|v| if v.is_empty() { "标准版本" } else { v.as_str() })
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            )
+        }
+    };
+
+    match variant {
+        Some(wanted) => find(wanted).ok_or_else(|| not_found(wanted)),
+        None => match default_variant() {
+            Some(auto) => find(auto).or_else(|| {
+                warn!("未找到 {} 变体的内核，使用标准版本", auto);
+                find("")
+            }),
+            None => find(""),
+        }
+        .ok_or_else(|| not_found("")),
+    }
+}
@@ -0,0 +1,219 @@
+//! 内核版本管理
+//!
+//! 每个版本安装在 sing-box/versions/<版本号> 目录中，当前使用的版本记录在状态文件里
+
+use crate::app::constants::{messages, paths, process};
+use crate::entity::kernel_model::{InstalledKernel, KernelInstallRecord, KernelVersionState};
+#[cfg(windows)]
+use std::os::windows::process::CommandExt;
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+use tracing::{info, warn};
+
+// 版本目录路径，版本号只允许字母、数字和 .-+，避免访问版本目录以外的文件
+pub fn version_dir(version: &str) -> Result<PathBuf, String> {
+    let valid = !version.is_empty()
+        && !version.starts_with('.')
+        && version.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '-' | '+'));
+    if !valid {
+        return Err(format!("无效的内核版本: {}", version));
+    }
+    Ok(paths::get_kernel_versions_dir().join(version))
+}
+
+// 指定版本的内核可执行文件路径
+pub fn version_kernel_path(version: &str) -> Result<PathBuf, String> {
+    Ok(version_dir(version)?.join(process::KERNEL_EXE_NAME))
+}
+
+// 读取版本状态，文件不存在或无法解析时返回默认状态
+pub fn load_state() -> KernelVersionState {
+    std::fs::read_to_string(paths::get_kernel_versions_state_path())
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default()
+}
+
+fn save_state(state: &KernelVersionState) -> Result<(), String> {
+    let path = paths::get_kernel_versions_state_path();
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent).map_err(|e| format!("{}: {}", messages::ERR_CREATE_DIR_FAILED, e))?;
+    }
+    let content = serde_json::to_string_pretty(state).map_err(|e| e.to_string())?;
+    std::fs::write(&path, content).map_err(|e| format!("{}: {}", messages::ERR_WRITE_FILE_FAILED, e))
+}
+
+// 安装记录文件路径
+fn install_record_path(version: &str) -> Result<PathBuf, String> {
+    Ok(version_dir(version)?.join("install.json"))
+}
+
+// 保存下载安装的资源和校验结果
+pub fn write_install_record(version: &str, record: &KernelInstallRecord) -> Result<(), String> {
+    let content = serde_json::to_string_pretty(record).map_err(|e| e.to_string())?;
+    std::fs::write(install_record_path(version)?, content)
+        .map_err(|e| format!("{}: {}", messages::ERR_WRITE_FILE_FAILED, e))
+}
+
+// 读取安装记录，手动放置或迁移的内核没有记录
+pub fn read_install_record(version: &str) -> Option<KernelInstallRecord> {
+    let content = std::fs::read_to_string(install_record_path(version).ok()?).ok()?;
+    serde_json::from_str(&content).ok()
+}
+
+// 当前使用的版本，未选择版本时为空
+pub fn active_version() -> Option<String> {
+    load_state().active
+}
+
+// 运行内核的 version 命令，返回输出内容
+pub fn kernel_version_info(kernel_path: &Path) -> Result<String, String> {
+    if !kernel_path.exists() {
+        return Err(messages::ERR_KERNEL_NOT_FOUND.to_string());
+    }
+
+    let mut command = std::process::Command::new(kernel_path);
+    command.arg("version");
+    #[cfg(windows)]
+    command.creation_flags(process::CREATE_NO_WINDOW);
+    let output = command
+        .output()
+        .map_err(|e| format!("{}: {}", messages::ERR_VERSION_CHECK_FAILED, e))?;
+
+    if !output.status.success() {
+        let error = String::from_utf8_lossy(&output.stderr);
+        return Err(format!("{}: {}", messages::ERR_GET_VERSION_FAILED, error));
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+// 从 version 命令的输出中解析版本号，例如 "sing-box version 1.11.4"
+pub fn parse_version(version_info: &str) -> Option<String> {
+    let first_line = version_info.lines().next()?;
+    let version = first_line.strip_prefix("sing-box version ")?.trim();
+    version_dir(version).ok()?;
+    Some(version.to_string())
+}
+
+// 获取所有已安装的版本，最新安装的在前
+pub fn list_installed() -> Vec<InstalledKernel> {
+    let entries = match std::fs::read_dir(paths::get_kernel_versions_dir()) {
+        Ok(entries) => entries,
+        Err(_) => return Vec::new(),
+    };
+    let state = load_state();
+
+    let mut versions: Vec<InstalledKernel> = entries
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.path().is_dir())
+        .filter_map(|entry| {
+            let version = entry.file_name().to_str()?.to_string();
+            let kernel_path = version_kernel_path(&version).ok()?;
+            if !kernel_path.exists() {
+                return None;
+            }
+            let installed_at = entry
+                .metadata()
+                .and_then(|m| m.modified())
+                .ok()
+                .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+                .map(|d| d.as_secs())
+                .unwrap_or(0);
+            let record = read_install_record(&version);
+            Some(InstalledKernel {
+                active: state.active.as_deref() == Some(version.as_str()),
+                previous: state.previous.as_deref() == Some(version.as_str()),
+                version_info: kernel_version_info(&kernel_path).ok(),
+                path: kernel_path.to_string_lossy().to_string(),
+                sha256: record.as_ref().map(|r| r.sha256.clone()),
+                verified: record.map(|r| r.verified).unwrap_or(false),
+                version,
+                installed_at,
+            })
+        })
+        .collect();
+
+    versions.sort_by(|a, b| b.installed_at.cmp(&a.installed_at).then_with(|| b.version.cmp(&a.version)));
+    versions
+}
+
+// 是否已安装指定版本
+pub fn is_installed(version: &str) -> bool {
+    version_kernel_path(version).map(|path| path.exists()).unwrap_or(false)
+}
+
+// 切换到指定版本，之前使用的版本记录为回滚目标
+pub fn set_active(version: &str) -> Result<(), String> {
+    if !is_installed(version) {
+        return Err(format!("内核版本 {} 未安装", version));
+    }
+
+    let mut state = load_state();
+    if state.active.as_deref() == Some(version) {
+        return Ok(());
+    }
+    state.previous = state.active.take();
+    state.active = Some(version.to_string());
+    save_state(&state)?;
+    info!("已切换内核版本: {}", version);
+    Ok(())
+}
+
+// 回滚到切换前使用的版本，返回回滚后的版本
+pub fn rollback() -> Result<String, String> {
+    let previous = load_state().previous.ok_or("没有可回滚的内核版本")?;
+    set_active(&previous)?;
+    Ok(previous)
+}
+
+// 删除已安装的版本，不能删除正在使用的版本
+pub fn remove_version(version: &str) -> Result<(), String> {
+    let dir = version_dir(version)?;
+    let mut state = load_state();
+    if state.active.as_deref() == Some(version) {
+        return Err("不能删除正在使用的内核版本".to_string());
+    }
+    if !dir.exists() {
+        return Err(format!("内核版本 {} 未安装", version));
+    }
+
+    std::fs::remove_dir_all(&dir).map_err(|e| format!("删除内核版本失败: {}", e))?;
+    if state.previous.as_deref() == Some(version) {
+        state.previous = None;
+        save_state(&state)?;
+    }
+    Ok(())
+}
+
+// 将旧版本直接放在 sing-box 目录下的内核移动到版本目录，并设为当前版本
+// 只在尚未选择版本时执行，移动后旧内核可以作为回滚目标
+pub fn migrate_legacy_kernel() {
+    if load_state().active.is_some() {
+        return;
+    }
+    let legacy_path = paths::get_kernel_work_dir().join(process::KERNEL_EXE_NAME);
+    if !legacy_path.exists() {
+        return;
+    }
+
+    let version = match kernel_version_info(&legacy_path).ok().as_deref().and_then(parse_version) {
+        Some(version) => version,
+        None => {
+            warn!("无法识别现有内核的版本，跳过迁移");
+            return;
+        }
+    };
+
+    let result = version_kernel_path(&version).and_then(|target| {
+        if let Some(parent) = target.parent() {
+            std::fs::create_dir_all(parent).map_err(|e| format!("{}: {}", messages::ERR_CREATE_DIR_FAILED, e))?;
+        }
+        std::fs::rename(&legacy_path, &target).map_err(|e| format!("移动内核文件失败: {}", e))?;
+        set_active(&version)
+    });
+    match result {
+        Ok(_) => info!("已将现有内核迁移到版本目录: {}", version),
+        Err(e) => warn!("迁移现有内核失败: {}", e),
+    }
+}
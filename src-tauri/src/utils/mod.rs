@@ -3,3 +3,12 @@ pub mod file_util;
 pub mod app_util;
 pub mod logger;
 pub mod proxy_util;
+pub mod share_link_util;
+pub mod subscription_util;
+pub mod node_rule_util;
+pub mod settings_util;
+pub mod snapshot_util;
+pub mod kernel_version_util;
+pub mod kernel_asset_util;
+pub mod verify_util;
+pub mod download_util;
@@ -0,0 +1,116 @@
+//! 订阅节点处理规则
+//!
+//! 在节点提取之后执行，按顺序进行过滤、重命名和去重
+
+use crate::entity::subscription_model::{NodeField, NodeFilter, NodeRules};
+use regex::Regex;
+use serde_json::{json, Value};
+use std::collections::HashSet;
+
+// 编译后的过滤条件
+struct CompiledFilter {
+    field: NodeField,
+    regex: Regex,
+}
+
+// 编译正则表达式，出错时返回包含规则内容的错误信息
+fn compile_regex(pattern: &str) -> Result<Regex, String> {
+    Regex::new(pattern).map_err(|e| format!("无效的正则表达式 \"{}\": {}", pattern, e))
+}
+
+fn compile_filters(filters: &[NodeFilter]) -> Result<Vec<CompiledFilter>, String> {
+    filters
+        .iter()
+        .map(|f| {
+            Ok(CompiledFilter {
+                field: f.field,
+                regex: compile_regex(&f.pattern)?,
+            })
+        })
+        .collect()
+}
+
+// 检查规则中的正则表达式是否有效
+pub fn validate_rules(rules: &NodeRules) -> Result<(), String> {
+    compile_filters(&rules.include)?;
+    compile_filters(&rules.exclude)?;
+    for rename in &rules.renames {
+        compile_regex(&rename.pattern)?;
+    }
+    Ok(())
+}
+
+// 对节点列表应用处理规则
+pub fn apply_node_rules(nodes: Vec<Value>, rules: &NodeRules) -> Result<Vec<Value>, String> {
+    let include = compile_filters(&rules.include)?;
+    let exclude = compile_filters(&rules.exclude)?;
+    let renames = rules
+        .renames
+        .iter()
+        .map(|r| Ok((compile_regex(&r.pattern)?, r.replacement.as_str())))
+        .collect::<Result<Vec<_>, String>>()?;
+
+    let mut seen = HashSet::new();
+    let mut result = Vec::new();
+
+    for mut node in nodes {
+        // 过滤
+        if !include.is_empty() && !include.iter().any(|f| filter_matches(f, &node)) {
+            continue;
+        }
+        if exclude.iter().any(|f| filter_matches(f, &node)) {
+            continue;
+        }
+
+        // 重命名，替换后为空时保留原名称
+        if !renames.is_empty() {
+            let original = node_field(&node, NodeField::Tag).to_string();
+            let mut tag = original.clone();
+            for (regex, replacement) in &renames {
+                tag = regex.replace_all(&tag, *replacement).into_owned();
+            }
+            let tag = tag.trim();
+            node["tag"] = json!(if tag.is_empty() { original.as_str() } else { tag });
+        }
+
+        // 去重
+        if rules.dedup {
+            if let Some(key) = dedup_key(&node) {
+                if !seen.insert(key) {
+                    continue;
+                }
+            }
+        }
+
+        result.push(node);
+    }
+
+    Ok(result)
+}
+
+fn filter_matches(filter: &CompiledFilter, node: &Value) -> bool {
+    filter.regex.is_match(node_field(node, filter.field))
+}
+
+fn node_field(node: &Value, field: NodeField) -> &str {
+    let key = match field {
+        NodeField::Tag => "tag",
+        NodeField::Type => "type",
+        NodeField::Server => "server",
+    };
+    node.get(key).and_then(|v| v.as_str()).unwrap_or("")
+}
+
+// 去重使用的键：服务器 + 端口 + 认证信息，没有服务器地址的节点不参与去重
+fn dedup_key(node: &Value) -> Option<String> {
+    let server = node.get("server").and_then(|v| v.as_str())?;
+    let port = node.get("server_port").and_then(|v| v.as_u64()).unwrap_or(0);
+
+    let credential = ["uuid", "password", "username", "auth_str", "private_key", "method"]
+        .iter()
+        .filter_map(|key| node.get(*key).and_then(|v| v.as_str()))
+        .collect::<Vec<_>>()
+        .join(":");
+
+    Some(format!("{}|{}|{}", server.to_ascii_lowercase(), port, credential))
+}
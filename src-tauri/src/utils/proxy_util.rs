@@ -1,8 +1,12 @@
 use std::io;
+#[cfg(windows)]
 use winreg::enums::*;
+#[cfg(windows)]
 use winreg::RegKey;
+#[cfg(windows)]
 use crate::app::constants::registry;
 
+#[cfg(windows)]
 pub fn disable_system_proxy() -> io::Result<()> {
     let hkcu = RegKey::predef(HKEY_CURRENT_USER);
     let settings = hkcu.open_subkey_with_flags(registry::INTERNET_SETTINGS, KEY_WRITE)?;
@@ -31,3 +35,9 @@ pub fn disable_system_proxy() -> io::Result<()> {
 
     Ok(())
 }
+
+// 其他系统的代理由桌面环境管理，这里无需处理
+#[cfg(not(windows))]
+pub fn disable_system_proxy() -> io::Result<()> {
+    Ok(())
+}
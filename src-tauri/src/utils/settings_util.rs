@@ -0,0 +1,55 @@
+//! 用户设置
+//!
+//! 设置保存在工作目录中，配置文件始终根据设置重新生成
+
+use crate::app::constants::{messages, paths};
+use crate::entity::settings_model::UserSettings;
+use once_cell::sync::Lazy;
+use std::sync::Mutex;
+use tracing::error;
+
+// 设置读写锁，避免并发修改时相互覆盖
+static SETTINGS_LOCK: Lazy<Mutex<()>> = Lazy::new(|| Mutex::new(()));
+
+// 读取用户设置，文件不存在或无法解析时返回默认设置
+pub fn load_settings() -> UserSettings {
+    let path = paths::get_settings_path();
+    if !path.exists() {
+        return UserSettings::default();
+    }
+
+    match std::fs::read_to_string(&path) {
+        Ok(content) => serde_json::from_str(&content).unwrap_or_else(|e| {
+            error!("解析用户设置失败: {}", e);
+            UserSettings::default()
+        }),
+        Err(e) => {
+            error!("{}: {}", messages::ERR_READ_FILE_FAILED, e);
+            UserSettings::default()
+        }
+    }
+}
+
+// 当前使用的 Clash API 端口
+pub fn clash_api_port() -> u16 {
+    load_settings().clash_api_port
+}
+
+// 保存用户设置
+fn save_settings(settings: &UserSettings) -> Result<(), String> {
+    let path = paths::get_settings_path();
+    let content = serde_json::to_string_pretty(settings).map_err(|e| e.to_string())?;
+    std::fs::write(&path, content).map_err(|e| format!("{}: {}", messages::ERR_WRITE_FILE_FAILED, e))
+}
+
+// 在锁内读取、修改并保存设置，返回修改后的设置
+pub fn update_settings<F>(f: F) -> Result<UserSettings, String>
+where
+    F: FnOnce(&mut UserSettings),
+{
+    let _guard = SETTINGS_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let mut settings = load_settings();
+    f(&mut settings);
+    save_settings(&settings)?;
+    Ok(settings)
+}
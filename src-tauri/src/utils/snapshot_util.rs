@@ -0,0 +1,172 @@
+//! 配置快照
+//!
+//! 每次写入配置文件前保存旧配置，保留有限数量的历史记录
+
+use crate::app::constants::{config, messages, paths};
+use crate::entity::snapshot_model::{ConfigDiff, ConfigSnapshot, DiffKind};
+use serde_json::Value;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+use tracing::warn;
+
+// 快照文件路径，ID只允许数字，避免访问快照目录以外的文件
+fn snapshot_path(id: &str) -> Result<PathBuf, String> {
+    if id.is_empty() || !id.chars().all(|c| c.is_ascii_digit()) {
+        return Err(format!("无效的快照ID: {}", id));
+    }
+    Ok(paths::get_config_snapshots_dir().join(format!("{}.json", id)))
+}
+
+// 保存配置文件当前内容的快照，返回快照ID
+// 文件不存在时返回空；内容与最新快照相同时直接返回最新快照的ID
+pub fn create_snapshot(config_path: &Path) -> Result<Option<String>, String> {
+    if !config_path.exists() {
+        return Ok(None);
+    }
+    let content = std::fs::read_to_string(config_path)
+        .map_err(|e| format!("{}: {}", messages::ERR_READ_FILE_FAILED, e))?;
+
+    if let Some(latest) = list_snapshots().into_iter().next() {
+        if read_snapshot(&latest.id).ok().as_deref() == Some(content.as_str()) {
+            return Ok(Some(latest.id));
+        }
+    }
+
+    let dir = paths::get_config_snapshots_dir();
+    std::fs::create_dir_all(&dir).map_err(|e| format!("{}: {}", messages::ERR_CREATE_DIR_FAILED, e))?;
+
+    // 以毫秒时间戳作为ID，同一毫秒内多次保存时顺延
+    let mut millis = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis())
+        .unwrap_or_default();
+    while dir.join(format!("{}.json", millis)).exists() {
+        millis += 1;
+    }
+    let id = millis.to_string();
+
+    std::fs::write(dir.join(format!("{}.json", id)), content)
+        .map_err(|e| format!("{}: {}", messages::ERR_WRITE_FILE_FAILED, e))?;
+    prune_snapshots();
+
+    Ok(Some(id))
+}
+
+// 获取所有快照，最新的在前
+pub fn list_snapshots() -> Vec<ConfigSnapshot> {
+    let entries = match std::fs::read_dir(paths::get_config_snapshots_dir()) {
+        Ok(entries) => entries,
+        Err(_) => return Vec::new(),
+    };
+
+    let mut snapshots: Vec<ConfigSnapshot> = entries
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| {
+            let path = entry.path();
+            if path.extension().and_then(|e| e.to_str()) != Some("json") {
+                return None;
+            }
+            let id = path.file_stem()?.to_str()?.to_string();
+            let millis = id.parse::<u64>().ok()?;
+            let size = entry.metadata().map(|m| m.len()).unwrap_or(0);
+            Some(ConfigSnapshot {
+                id,
+                created_at: millis / 1000,
+                size,
+            })
+        })
+        .collect();
+
+    snapshots.sort_by(|a, b| b.id.len().cmp(&a.id.len()).then_with(|| b.id.cmp(&a.id)));
+    snapshots
+}
+
+// 读取快照内容
+pub fn read_snapshot(id: &str) -> Result<String, String> {
+    let path = snapshot_path(id)?;
+    if !path.exists() {
+        return Err(format!("快照不存在: {}", id));
+    }
+    std::fs::read_to_string(&path).map_err(|e| format!("{}: {}", messages::ERR_READ_FILE_FAILED, e))
+}
+
+// 删除超出数量限制的旧快照
+fn prune_snapshots() {
+    for snapshot in list_snapshots().into_iter().skip(config::MAX_SNAPSHOTS) {
+        if let Ok(path) = snapshot_path(&snapshot.id) {
+            if let Err(e) = std::fs::remove_file(&path) {
+                warn!("删除旧快照 {} 失败: {}", snapshot.id, e);
+            }
+        }
+    }
+}
+
+// 比较两个JSON值，返回所有差异
+pub fn diff_json(old: &Value, new: &Value) -> Vec<ConfigDiff> {
+    let mut diffs = Vec::new();
+    diff_value("", old, new, &mut diffs);
+    diffs
+}
+
+fn diff_value(path: &str, old: &Value, new: &Value, diffs: &mut Vec<ConfigDiff>) {
+    match (old, new) {
+        (Value::Object(old_map), Value::Object(new_map)) => {
+            for (key, old_value) in old_map {
+                let child = format!("{}/{}", path, escape_pointer(key));
+                match new_map.get(key) {
+                    Some(new_value) => diff_value(&child, old_value, new_value, diffs),
+                    None => diffs.push(removed(child, old_value)),
+                }
+            }
+            for (key, new_value) in new_map {
+                if !old_map.contains_key(key) {
+                    diffs.push(added(format!("{}/{}", path, escape_pointer(key)), new_value));
+                }
+            }
+        }
+        (Value::Array(old_items), Value::Array(new_items)) => {
+            for i in 0..old_items.len().max(new_items.len()) {
+                let child = format!("{}/{}", path, i);
+                match (old_items.get(i), new_items.get(i)) {
+                    (Some(o), Some(n)) => diff_value(&child, o, n, diffs),
+                    (Some(o), None) => diffs.push(removed(child, o)),
+                    (None, Some(n)) => diffs.push(added(child, n)),
+                    (None, None) => {}
+                }
+            }
+        }
+        _ => {
+            if old != new {
+                diffs.push(ConfigDiff {
+                    path: path.to_string(),
+                    kind: DiffKind::Changed,
+                    old: Some(old.clone()),
+                    new: Some(new.clone()),
+                });
+            }
+        }
+    }
+}
+
+fn added(path: String, value: &Value) -> ConfigDiff {
+    ConfigDiff {
+        path,
+        kind: DiffKind::Added,
+        old: None,
+        new: Some(value.clone()),
+    }
+}
+
+fn removed(path: String, value: &Value) -> ConfigDiff {
+    ConfigDiff {
+        path,
+        kind: DiffKind::Removed,
+        old: Some(value.clone()),
+        new: None,
+    }
+}
+
+// JSON Pointer 转义
+fn escape_pointer(key: &str) -> String {
+    key.replace('~', "~0").replace('/', "~1")
+}
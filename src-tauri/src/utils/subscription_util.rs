@@ -0,0 +1,189 @@
+//! 订阅注册表
+//!
+//! 在工作目录中保存多个命名订阅的信息，以及每个订阅最近一次拉取到的节点
+
+use crate::app::constants::{messages, paths};
+use crate::entity::subscription_model::{SubscriptionRegistry, SubscriptionUserInfo};
+use once_cell::sync::Lazy;
+use serde_json::{json, Value};
+use std::collections::{HashMap, HashSet};
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tracing::{error, warn};
+
+// 注册表读写锁，避免并发修改时相互覆盖
+static REGISTRY_LOCK: Lazy<Mutex<()>> = Lazy::new(|| Mutex::new(()));
+
+// 读取订阅注册表，文件不存在时返回空注册表
+pub fn load_registry() -> Result<SubscriptionRegistry, String> {
+    let _guard = REGISTRY_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    read_registry()
+}
+
+// 读取注册表文件，读取或解析失败时返回错误，避免随后保存时覆盖原有订阅
+fn read_registry() -> Result<SubscriptionRegistry, String> {
+    let path = paths::get_subscriptions_path();
+    if !path.exists() {
+        return Ok(SubscriptionRegistry::default());
+    }
+
+    let content = std::fs::read_to_string(&path).map_err(|e| {
+        error!("{}: {}", messages::ERR_READ_FILE_FAILED, e);
+        format!("{}: {}", messages::ERR_READ_FILE_FAILED, e)
+    })?;
+    serde_json::from_str(&content).map_err(|e| {
+        error!("解析订阅注册表失败: {}", e);
+        format!("解析订阅注册表失败: {}", e)
+    })
+}
+
+// 保存订阅注册表：先写入临时文件再替换，避免写入中断导致注册表损坏
+fn save_registry(registry: &SubscriptionRegistry) -> Result<(), String> {
+    let path = paths::get_subscriptions_path();
+    let content = serde_json::to_string_pretty(registry).map_err(|e| e.to_string())?;
+
+    let tmp_path = path.with_extension("json.tmp");
+    std::fs::write(&tmp_path, content).map_err(|e| format!("{}: {}", messages::ERR_WRITE_FILE_FAILED, e))?;
+    std::fs::rename(&tmp_path, &path).map_err(|e| {
+        let _ = std::fs::remove_file(&tmp_path);
+        format!("{}: {}", messages::ERR_WRITE_FILE_FAILED, e)
+    })
+}
+
+// 在锁内读取、修改并保存注册表
+pub fn update_registry<T, F>(f: F) -> Result<T, String>
+where
+    F: FnOnce(&mut SubscriptionRegistry) -> Result<T, String>,
+{
+    let _guard = REGISTRY_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let mut registry = read_registry()?;
+    let result = f(&mut registry)?;
+    save_registry(&registry)?;
+    Ok(result)
+}
+
+// 读取订阅缓存的节点
+pub fn load_nodes(id: &str) -> Vec<Value> {
+    let path = paths::get_subscription_nodes_dir().join(format!("{}.json", id));
+    match std::fs::read_to_string(&path) {
+        Ok(content) => serde_json::from_str(&content).unwrap_or_else(|e| {
+            warn!("解析订阅 {} 的节点缓存失败: {}", id, e);
+            Vec::new()
+        }),
+        Err(_) => Vec::new(),
+    }
+}
+
+// 保存订阅拉取到的节点
+pub fn save_nodes(id: &str, nodes: &[Value]) -> Result<(), String> {
+    let dir = paths::get_subscription_nodes_dir();
+    std::fs::create_dir_all(&dir).map_err(|e| format!("{}: {}", messages::ERR_CREATE_DIR_FAILED, e))?;
+
+    let content = serde_json::to_string_pretty(nodes).map_err(|e| e.to_string())?;
+    std::fs::write(dir.join(format!("{}.json", id)), content)
+        .map_err(|e| format!("{}: {}", messages::ERR_WRITE_FILE_FAILED, e))
+}
+
+// 删除订阅的节点缓存
+pub fn remove_nodes(id: &str) {
+    let path = paths::get_subscription_nodes_dir().join(format!("{}.json", id));
+    if path.exists() {
+        if let Err(e) = std::fs::remove_file(&path) {
+            warn!("删除订阅 {} 的节点缓存失败: {}", id, e);
+        }
+    }
+}
+
+// 生成新的订阅ID
+pub fn generate_subscription_id() -> String {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_nanos())
+        .unwrap_or_default();
+    format!("{:x}", nanos)
+}
+
+// 当前Unix时间戳(秒)
+pub fn now_timestamp() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+// 为订阅的节点标签添加前缀，避免不同订阅之间的标签冲突
+pub fn prefix_node_tags(subscription_name: &str, nodes: Vec<Value>, used_tags: &mut HashSet<String>) -> Vec<Value> {
+    let mut renamed = HashMap::new();
+    let mut result = Vec::with_capacity(nodes.len());
+
+    for mut node in nodes {
+        let tag = node.get("tag").and_then(|t| t.as_str()).unwrap_or("unknown").to_string();
+        let base = format!("[{}] {}", subscription_name, tag);
+
+        // 同一订阅中存在同名节点时追加序号
+        let mut new_tag = base.clone();
+        let mut index = 2;
+        while used_tags.contains(&new_tag) {
+            new_tag = format!("{} #{}", base, index);
+            index += 1;
+        }
+        used_tags.insert(new_tag.clone());
+        renamed.entry(tag).or_insert_with(|| new_tag.clone());
+
+        node["tag"] = json!(new_tag);
+        result.push(node);
+    }
+
+    // 同步更新订阅内部的 detour 引用
+    for node in result.iter_mut() {
+        let detour = node.get("detour").and_then(|d| d.as_str()).map(|d| d.to_string());
+        if let Some(new_detour) = detour.and_then(|d| renamed.get(&d)) {
+            node["detour"] = json!(new_detour);
+        }
+    }
+
+    result
+}
+
+// 解析 subscription-userinfo 响应头，例如 upload=1; download=2; total=3; expire=4
+pub fn parse_subscription_userinfo(header: &str) -> Option<SubscriptionUserInfo> {
+    let mut info = SubscriptionUserInfo::default();
+    let mut found = false;
+
+    for part in header.split(';') {
+        let (key, value) = match part.split_once('=') {
+            Some(pair) => pair,
+            None => continue,
+        };
+        // 部分服务商会返回浮点数或科学计数法
+        let value = value.trim();
+        let number = match value
+            .parse::<u64>()
+            .ok()
+            .or_else(|| value.parse::<f64>().ok().filter(|v| *v >= 0.0).map(|v| v as u64))
+        {
+            Some(number) => number,
+            None => continue,
+        };
+
+        match key.trim().to_ascii_lowercase().as_str() {
+            "upload" => info.upload = number,
+            "download" => info.download = number,
+            "total" => info.total = number,
+            "expire" => info.expire = if number > 0 { Some(number) } else { None },
+            _ => continue,
+        }
+        found = true;
+    }
+
+    if found {
+        Some(info)
+    } else {
+        None
+    }
+}
+
+// 解析 profile-update-interval 响应头(小时)
+pub fn parse_profile_update_interval(header: &str) -> Option<u64> {
+    header.trim().parse::<u64>().ok().filter(|hours| *hours > 0)
+}
@@ -0,0 +1,133 @@
+//! 下载文件校验
+//!
+//! 文件可能经由第三方镜像下载，安装前需要与 GitHub 上发布的校验值或签名比对
+
+use crate::app::constants::api;
+use crate::entity::github_model::Asset;
+use crate::utils::download_util;
+use minisign_verify::{PublicKey, Signature};
+use sha2::{Digest, Sha256};
+use std::fs::File;
+use std::path::Path;
+use tracing::{info, warn};
+
+// 计算文件的 SHA-256，返回小写十六进制字符串
+pub fn sha256_file(path: &Path) -> Result<String, String> {
+    let mut file = File::open(path).map_err(|e| format!("打开文件失败: {}", e))?;
+    let mut hasher = Sha256::new();
+    std::io::copy(&mut file, &mut hasher).map_err(|e| format!("读取文件失败: {}", e))?;
+    Ok(hasher.finalize().iter().map(|b| format!("{:02x}", b)).collect())
+}
+
+// 查找资源发布的 SHA-256
+// 优先使用 GitHub 提供的资源摘要，其次是发布中的 <文件名>.sha256 或校验和列表文件
+pub async fn expected_sha256(assets: &[Asset], asset: &Asset) -> Result<Option<String>, String> {
+    if let Some(digest) = asset.digest.as_deref().and_then(|d| d.strip_prefix("sha256:")) {
+        return Ok(Some(digest.to_lowercase()));
+    }
+
+    let single = [format!("{}.sha256", asset.name), format!("{}.sha256sum", asset.name)];
+    if let Some(file) = assets.iter().find(|a| single.contains(&a.name)) {
+        let content = fetch_text(&file.browser_download_url).await?;
+        return Ok(content.split_whitespace().next().map(|hash| hash.to_lowercase()));
+    }
+
+    if let Some(file) = assets.iter().find(|a| is_checksum_list(&a.name)) {
+        let content = fetch_text(&file.browser_download_url).await?;
+        return Ok(find_checksum(&content, &asset.name));
+    }
+
+    Ok(None)
+}
+
+// 校验和列表文件，例如 checksums.txt、SHA256SUMS
+fn is_checksum_list(name: &str) -> bool {
+    let name = name.to_lowercase();
+    name.contains("checksums") || name.starts_with("sha256sums")
+}
+
+// 从 "<哈希>  <文件名>" 格式的列表中查找文件的哈希
+fn find_checksum(content: &str, file_name: &str) -> Option<String> {
+    content.lines().find_map(|line| {
+        let mut parts = line.split_whitespace();
+        let hash = parts.next()?;
+        let name = parts.next()?.trim_start_matches('*');
+        (name == file_name && hash.len() == 64).then(|| hash.to_lowercase())
+    })
+}
+
+// 校验文件的 SHA-256，返回实际的哈希
+// 没有发布校验值时只记录警告，不一致时返回错误
+pub fn verify_sha256(path: &Path, expected: Option<&str>) -> Result<String, String> {
+    let actual = sha256_file(path)?;
+    match expected {
+        Some(expected) if !expected.eq_ignore_ascii_case(&actual) => Err(format!(
+            "文件校验失败，期望 SHA-256 为 {}，实际为 {}",
+            expected, actual
+        )),
+        Some(_) => {
+            info!("文件校验通过: {} (SHA-256: {})", path.display(), actual);
+            Ok(actual)
+        }
+        None => {
+            warn!("未找到发布的校验值，无法校验: {} (SHA-256: {})", path.display(), actual);
+            Ok(actual)
+        }
+    }
+}
+
+// 使用 minisign 校验文件签名，签名和公钥均为 Tauri 更新器使用的 base64 格式
+pub fn verify_signature(path: &Path, signature: &str, public_key: &str) -> Result<(), String> {
+    let public_key = decode_public_key(public_key)?;
+    let signature = decode_base64_text(signature)
+        .and_then(|sig| Signature::decode(&sig).ok())
+        .ok_or("更新签名格式错误")?;
+
+    let data = std::fs::read(path).map_err(|e| format!("读取文件失败: {}", e))?;
+    public_key
+        .verify(&data, &signature, true)
+        .map_err(|e| format!("签名校验失败: {}", e))
+}
+
+// 解析 base64 格式的公钥，下载前用于确认公钥可用
+pub fn decode_public_key(public_key: &str) -> Result<PublicKey, String> {
+    decode_base64_text(public_key)
+        .and_then(|key| PublicKey::decode(&key).ok())
+        .ok_or_else(|| "更新签名公钥格式错误".to_string())
+}
+
+fn decode_base64_text(value: &str) -> Option<String> {
+    base64::decode(value.trim())
+        .ok()
+        .and_then(|bytes| String::from_utf8(bytes).ok())
+}
+
+// 直接从 GitHub 获取文本文件，校验值不经过镜像，避免与被篡改的文件一起被替换
+pub async fn fetch_text(url: &str) -> Result<String, String> {
+    let response = reqwest::Client::new()
+        .get(url)
+        .header("User-Agent", api::USER_AGENT)
+        .send()
+        .await
+        .map_err(|e| format!("获取 {} 失败: {}", url, e))?;
+    if !response.status().is_success() {
+        return Err(format!("获取 {} 失败: {}", url, response.status()));
+    }
+    response.text().await.map_err(|e| format!("获取 {} 失败: {}", url, e))
+}
+
+// 获取签名文件，直接访问失败时通过下载镜像获取；签名由公钥校验，不依赖下载来源
+pub async fn fetch_signature(url: &str) -> Result<String, String> {
+    let mut last_error = match fetch_text(url).await {
+        Ok(text) => return Ok(text),
+        Err(e) => e,
+    };
+    for mirror_url in download_util::mirror_urls(url).iter().filter(|u| u.as_str() != url) {
+        info!("{}，尝试通过镜像获取", last_error);
+        match fetch_text(mirror_url).await {
+            Ok(text) => return Ok(text),
+            Err(e) => last_error = e,
+        }
+    }
+    Err(last_error)
+}